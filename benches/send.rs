@@ -0,0 +1,115 @@
+use std::time::Instant;
+
+use apns_h2::{Client, ClientConfig, DefaultNotificationBuilder, NotificationBuilder};
+use http_body_util::{BodyExt, Empty};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::HeaderValue;
+use hyper::server::conn::http2;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use tokio::net::TcpListener;
+
+// Measures the throughput of the send path against a local cleartext HTTP/2
+// server accepting every notification, leaving out TLS and the network, with
+// the default and with the adaptive HTTP/2 flow control window
+const NOTIFICATIONS: usize = 100_000;
+const CONCURRENCY: usize = 100;
+
+async fn accept(request: hyper::Request<Incoming>) -> Result<hyper::Response<Empty<Bytes>>, hyper::Error> {
+    // Responding before the body is read would reset the stream
+    request.into_body().collect().await?;
+
+    let mut response = hyper::Response::new(Empty::new());
+    response.headers_mut().insert(
+        "apns-id",
+        HeaderValue::from_static("00000000-0000-0000-0000-000000000001"),
+    );
+
+    Ok(response)
+}
+
+async fn serve(listener: TcpListener) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            return;
+        };
+
+        tokio::spawn(async move {
+            let _ = http2::Builder::new(TokioExecutor::new())
+                .max_concurrent_streams(1000)
+                .serve_connection(TokioIo::new(stream), service_fn(accept))
+                .await;
+        });
+    }
+}
+
+/// Sends `NOTIFICATIONS` through `client` and prints the throughput as `name`
+async fn run(name: &str, client: Client) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let start = Instant::now();
+    let mut senders = Vec::with_capacity(CONCURRENCY);
+
+    for _ in 0..CONCURRENCY {
+        let client = client.clone();
+
+        senders.push(tokio::spawn(async move {
+            for _ in 0..NOTIFICATIONS / CONCURRENCY {
+                let payload = DefaultNotificationBuilder::new()
+                    .title("a title")
+                    .body("a body that is a bit longer than the title")
+                    .build("a1b2c3d4", Default::default());
+
+                client.send(payload).await?;
+            }
+
+            Ok::<_, apns_h2::Error>(())
+        }));
+    }
+
+    for sender in senders {
+        sender.await??;
+    }
+
+    let elapsed = start.elapsed();
+
+    println!(
+        "{:<20} {:>10.0} notifications/s {:>8.0} ns/notification",
+        name,
+        NOTIFICATIONS as f64 / elapsed.as_secs_f64(),
+        elapsed.as_nanos() as f64 / NOTIFICATIONS as f64,
+    );
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+
+    tokio::spawn(serve(listener));
+
+    let key = include_bytes!("../test_cert/test.key");
+    let cert = include_bytes!("../test_cert/test.crt");
+    let config = ClientConfig {
+        resolve_override: Some(addr),
+        plaintext_h2: true,
+        ..Default::default()
+    };
+
+    // Every client opens a connection of its own
+    run("send", Client::certificate_parts(cert, key, config.clone())?).await?;
+    run(
+        "send adaptive_window",
+        Client::certificate_parts(
+            cert,
+            key,
+            ClientConfig {
+                adaptive_window: true,
+                ..config
+            },
+        )?,
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use apns_h2::request::payload::PayloadLike;
+use apns_h2::{DefaultNotificationBuilder, NotificationBuilder};
+
+// Compares allocations and time of `to_json_string` against reusing one buffer
+// with `serialize_into`, and of adding large custom data as a `Value` against
+// streaming it
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const ITERATIONS: usize = 100_000;
+
+fn measure(name: &str, mut f: impl FnMut()) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        f();
+    }
+
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    println!(
+        "{:<16} {:>8.1} allocations/iter {:>8.0} ns/iter",
+        name,
+        allocations as f64 / ITERATIONS as f64,
+        elapsed.as_nanos() as f64 / ITERATIONS as f64,
+    );
+}
+
+fn main() {
+    let payload = DefaultNotificationBuilder::new()
+        .title("a title")
+        .body("a body that is a bit longer than the title")
+        .badge(42)
+        .sound("ping.flac")
+        .build("a1b2c3d4", Default::default());
+
+    measure("to_json_string", || {
+        black_box(payload.to_json_string().unwrap());
+    });
+
+    let mut buf = Vec::new();
+
+    measure("serialize_into", || {
+        payload.serialize_into(&mut buf).unwrap();
+        black_box(&buf);
+    });
+
+    let blob: Vec<String> = (0..100).map(|i| format!("entry number {i}")).collect();
+
+    measure("custom_data", || {
+        let mut payload = DefaultNotificationBuilder::new()
+            .content_available()
+            .build("a1b2c3d4", Default::default());
+
+        payload.add_custom_data("blob", &blob).unwrap();
+        payload.serialize_into(&mut buf).unwrap();
+        black_box(&buf);
+    });
+
+    measure("custom_streaming", || {
+        let mut payload = DefaultNotificationBuilder::new()
+            .content_available()
+            .build("a1b2c3d4", Default::default());
+
+        payload.add_custom_data_streaming("blob", &blob);
+        payload.serialize_into(&mut buf).unwrap();
+        black_box(&buf);
+    });
+}
@@ -1,30 +1,52 @@
 //! The client module for sending requests and parsing responses
 
+use crate::clock::{Clock, SystemClock};
+use crate::connections::{ConnectionTracker, RecordingVerifier, find_pin_mismatch};
+use crate::dedup::DedupCache;
 use crate::error::Error;
 use crate::error::Error::ResponseError;
+use crate::id::IdGenerator;
+use crate::retry::RetryPolicy;
 use crate::signer::Signer;
-use tokio::time::timeout;
+use tokio::sync::{Semaphore, SemaphorePermit, mpsc};
+use tokio::time::{sleep, timeout};
 
-use crate::request::payload::PayloadLike;
-use crate::response::Response;
-use http::header::{AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE};
+use crate::request::channel::{Channel, ChannelBody, ChannelOperation};
+use crate::request::headers::RequestHeaders;
+use crate::request::notification::{
+    DefaultNotificationBuilder, NotificationBuilder, NotificationOptions, PushType, validate_collapse_id,
+    validate_topic,
+};
+use crate::request::payload::{Payload, PayloadLike, minimize_aps};
+use crate::request::target::Target;
+use crate::response::{AuditRecord, CampaignReport, Delivery, ErrorBody, ErrorReason, Response, VerboseResponse};
+use http::HeaderMap;
+use http::header::{AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, HeaderValue};
 use http_body_util::combinators::BoxBody;
-use http_body_util::{BodyExt, Full};
+use http_body_util::{BodyExt, Empty, Full};
 use hyper::body::Bytes;
 use hyper::{self, StatusCode};
 use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
 use hyper_util::client::legacy::Client as HttpClient;
-use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::connect::dns::{GaiResolver, Name};
+use hyper_util::client::legacy::connect::{HttpConnector, HttpInfo};
 use hyper_util::rt::{TokioExecutor, TokioTimer};
+use std::collections::HashMap;
 use std::convert::Infallible;
+use std::future::Future;
 use std::io::Read;
+use std::net::SocketAddr;
+use std::pin::Pin;
 use std::sync::Arc;
-use std::time::Duration;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, UNIX_EPOCH};
 use std::{fmt, io};
+use time::OffsetDateTime;
+use tower_service::Service;
 
 const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 20;
 
-type HyperConnector = HttpsConnector<HttpConnector>;
+type HyperConnector = HttpsConnector<HttpConnector<Resolver>>;
 
 /// The APNs service endpoint to connect.
 #[derive(Debug, Clone)]
@@ -37,12 +59,54 @@ pub enum Endpoint {
 
 impl fmt::Display for Endpoint {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let host = match self {
+        f.write_str(self.host())
+    }
+}
+
+impl Endpoint {
+    /// The production environment, for apps from the App Store or TestFlight
+    pub const fn production() -> Self {
+        Endpoint::Production
+    }
+
+    /// The development environment, for apps built with a development
+    /// provisioning profile
+    pub const fn sandbox() -> Self {
+        Endpoint::Sandbox
+    }
+
+    /// The name of the environment, `production` or `sandbox`
+    ///
+    /// ```rust
+    /// # use apns_h2::Endpoint;
+    /// let endpoint = Endpoint::sandbox();
+    ///
+    /// assert_eq!(
+    ///     "sending via sandbox (api.sandbox.push.apple.com)",
+    ///     format!("sending via {} ({})", endpoint.name(), endpoint.host())
+    /// );
+    /// ```
+    pub fn name(&self) -> &'static str {
+        match self {
+            Endpoint::Production => "production",
+            Endpoint::Sandbox => "sandbox",
+        }
+    }
+
+    /// The host notifications are sent to
+    pub fn host(&self) -> &'static str {
+        match self {
             Endpoint::Production => "api.push.apple.com",
             Endpoint::Sandbox => "api.sandbox.push.apple.com",
-        };
+        }
+    }
 
-        write!(f, "{}", host)
+    /// The host of the broadcast channel management API
+    fn channel_management_host(&self) -> &'static str {
+        match self {
+            Endpoint::Production => "api-manage-broadcast.push.apple.com:2196",
+            Endpoint::Sandbox => "api-manage-broadcast.sandbox.push.apple.com:2195",
+        }
     }
 }
 
@@ -60,18 +124,190 @@ pub struct Client {
     http_client: HttpClient<HyperConnector, BoxBody<Bytes, Infallible>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 /// The default implementation uses [`Endpoint::Production`] and can be created
 /// trough calling [`ClientConfig::default`].
 pub struct ClientConfig {
     /// The endpoint where the requests are sent to
     pub endpoint: Endpoint,
-    /// The timeout of the HTTP requests
+    /// The timeout of the HTTP requests. A timed out request fails with
+    /// [`Error::RequestTimeout`] and only cancels its own HTTP/2 stream, other
+    /// requests on the same connection continue.
     pub request_timeout_secs: Option<u64>,
-    /// The timeout for idle sockets being kept alive
+    /// The timeout for opening a TCP connection to APNs. Without it,
+    /// connecting is only limited by the request timeout.
+    pub connect_timeout_secs: Option<u64>,
+    /// Connect to this address instead of resolving the APNs host, e.g. to
+    /// pin APNs to a known IP. TLS still verifies the certificate for the
+    /// APNs host name. A port of 0 keeps the port of the APNs service.
+    pub resolve_override: Option<SocketAddr>,
+    /// SHA-256 hashes of the DER-encoded subject public key info of
+    /// certificates APNs must present. Connections fail with
+    /// [`Error::CertificatePinMismatch`] unless the leaf or an intermediate
+    /// certificate matches one of them. Not checked by TLS configs from
+    /// [`build_client_tls_config`], which know nothing about the client.
+    pub pinned_spki: Option<Vec<Vec<u8>>>,
+    /// Send over cleartext HTTP/2 (h2c) instead of TLS, e.g. to benchmark
+    /// against a local server set with [`ClientConfig::resolve_override`].
+    /// Only available with the `test-util` feature, APNs requires TLS.
+    #[cfg(any(test, feature = "test-util"))]
+    pub plaintext_h2: bool,
+    /// The timeout for idle connections being kept alive, in seconds.
+    /// [`ClientConfig::idle_timeout`] takes precedence if set.
     pub pool_idle_timeout_secs: Option<u64>,
+    /// Close the connection to APNs after no notification was sent over it
+    /// for this long, to free its resources. The next send opens a new
+    /// connection. Without a timeout, the connection is kept open until APNs
+    /// closes it.
+    pub idle_timeout: Option<Duration>,
     pub http2_keep_alive_interval_secs: Option<u64>,
     pub http2_keep_alive_while_idle: bool,
+    /// The maximum number of requests in flight at the same time, shared by
+    /// all clones of the client. Further sends wait until a request finishes.
+    pub max_inflight: Option<usize>,
+    /// The time source for issuing and renewing authentication tokens and
+    /// for timestamping [`Client::update_live_activity`]
+    pub clock: Arc<dyn Clock>,
+    /// Fields added to the JWT header of the provider tokens for token based
+    /// authentication, replacing the `kid` set by the client if named the
+    /// same, e.g. when APNs changes its requirements. See
+    /// [`Client::jwt_header`] for the resulting header. Building the client
+    /// fails with [`Error::InvalidOptions`] for an `alg` other than `ES256`.
+    pub jwt_header: serde_json::Map<String, serde_json::Value>,
+    /// Options used for every notification that doesn't set them itself
+    pub default_options: NotificationOptions<'static>,
+    /// The `apns-topic` of notifications without one, by push type, e.g.
+    /// with the `.voip` suffix for [`PushType::Voip`]. The push type is the
+    /// `apns_push_type` of the options, the one required by the suffix of the
+    /// default topic, or else guessed from the payload.
+    /// Takes precedence over the topic of [`ClientConfig::default_options`].
+    pub topic_for: HashMap<PushType, String>,
+    /// Don't guess [`PushType::Background`] for notifications without an
+    /// `apns_push_type` that only set `content-available`, and treat them as
+    /// [`PushType::Alert`] instead, e.g. when alerts are filled in by a
+    /// notification service extension.
+    pub strict_push_type_inference: bool,
+    /// Check notifications sent with an `apns_push_type` or an
+    /// `apns-push-type` header against the rules of that push type with
+    /// [`PayloadLike::revalidate`] before sending, e.g. when a payload built
+    /// for one push type is sent as another. Fails with the error of the
+    /// check, like [`Error::ConflictingFields`]. Enabled by default, disable
+    /// it to send payloads as they are.
+    pub revalidate_push_type: bool,
+    /// Suppress sending a notification again while it is being sent and
+    /// within this window after it was accepted by APNs, as measured by the
+    /// [`ClientConfig::clock`]. Notifications are the same if they have the
+    /// same target, collapse ID and body. A suppressed send fails with
+    /// [`Error::Duplicate`].
+    pub dedup_window: Option<Duration>,
+    /// The largest payload in bytes sent for a push type. Larger payloads
+    /// fail with [`Error::PayloadTooLarge`] without being sent. Push types
+    /// missing from the map use [`PushType::max_payload_bytes`].
+    pub max_payload_bytes: HashMap<PushType, usize>,
+    /// Settings advertised to APNs for the HTTP/2 connection
+    pub h2_settings: H2Settings,
+    /// Let HTTP/2 grow the flow control windows based on the measured
+    /// round-trip time, e.g. for high-latency links. Overrides the window
+    /// sizes of [`ClientConfig::h2_settings`].
+    pub adaptive_window: bool,
+    /// Generates the `apns-id` of notifications sent without one, e.g.
+    /// [`RandomIdGenerator`](crate::RandomIdGenerator). Without a generator
+    /// APNs assigns the ID and returns it in [`Response::apns_id`].
+    pub apns_id_generator: Option<Arc<dyn IdGenerator>>,
+    /// Leave out the keys of the `aps` object that are set to a default
+    /// value, like `"mutable-content": 0` or an empty `loc-args`, to keep
+    /// payloads small. A `badge` of 0 is kept, as it removes the badge.
+    pub minimize_payload: bool,
+    /// Called with the JSON of every notification right before it is sent,
+    /// e.g. to add a key to all notifications
+    pub on_serialize: Option<Arc<SerializeFn>>,
+    /// Called with the device token and, for `Unregistered`, the time APNs
+    /// confirmed it invalid, when APNs rejects a notification with
+    /// [`ErrorReason::Unregistered`] or [`ErrorReason::BadDeviceToken`], e.g.
+    /// to delete the token from storage
+    pub on_invalid_token: Option<Arc<InvalidTokenFn>>,
+    /// Decides whether and when failed sends are retried. Without a policy,
+    /// failed sends are not retried.
+    pub retry_policy: Option<Arc<dyn RetryPolicy>>,
+}
+
+impl fmt::Debug for ClientConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ClientConfig");
+
+        debug
+            .field("endpoint", &self.endpoint)
+            .field("request_timeout_secs", &self.request_timeout_secs)
+            .field("connect_timeout_secs", &self.connect_timeout_secs)
+            .field("resolve_override", &self.resolve_override)
+            .field("pinned_spki", &self.pinned_spki);
+
+        #[cfg(any(test, feature = "test-util"))]
+        debug.field("plaintext_h2", &self.plaintext_h2);
+
+        debug
+            .field("pool_idle_timeout_secs", &self.pool_idle_timeout_secs)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("http2_keep_alive_interval_secs", &self.http2_keep_alive_interval_secs)
+            .field("http2_keep_alive_while_idle", &self.http2_keep_alive_while_idle)
+            .field("max_inflight", &self.max_inflight)
+            .field("clock", &self.clock)
+            .field("jwt_header", &self.jwt_header)
+            .field("default_options", &self.default_options)
+            .field("topic_for", &self.topic_for)
+            .field("strict_push_type_inference", &self.strict_push_type_inference)
+            .field("revalidate_push_type", &self.revalidate_push_type)
+            .field("dedup_window", &self.dedup_window)
+            .field("max_payload_bytes", &self.max_payload_bytes)
+            .field("h2_settings", &self.h2_settings)
+            .field("adaptive_window", &self.adaptive_window)
+            .field("apns_id_generator", &self.apns_id_generator)
+            .field("minimize_payload", &self.minimize_payload)
+            .field("on_serialize", &self.on_serialize.as_ref().map(|_| "Fn"))
+            .field("on_invalid_token", &self.on_invalid_token.as_ref().map(|_| "Fn"))
+            .field("retry_policy", &self.retry_policy)
+            .finish()
+    }
+}
+
+/// The hook of [`ClientConfig::on_serialize`]
+pub type SerializeFn = dyn Fn(&mut serde_json::Value) + Send + Sync;
+
+#[derive(Clone)]
+struct SerializeHook(Arc<SerializeFn>);
+
+impl fmt::Debug for SerializeHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SerializeHook")
+    }
+}
+
+/// The hook of [`ClientConfig::on_invalid_token`]
+pub type InvalidTokenFn = dyn Fn(&str, Option<u64>) + Send + Sync;
+
+#[derive(Clone)]
+struct InvalidTokenHook(Arc<InvalidTokenFn>);
+
+impl fmt::Debug for InvalidTokenHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("InvalidTokenHook")
+    }
+}
+
+/// Tuning of the HTTP/2 connection. Settings left at `None` use the hyper
+/// defaults. Server push is always disabled.
+#[derive(Debug, Clone, Default)]
+pub struct H2Settings {
+    /// `SETTINGS_HEADER_TABLE_SIZE` for the HPACK decoder
+    pub header_table_size: Option<u32>,
+    /// `SETTINGS_MAX_FRAME_SIZE`, the largest frame payload accepted
+    pub max_frame_size: Option<u32>,
+    /// `SETTINGS_MAX_HEADER_LIST_SIZE`, the largest header list accepted
+    pub max_header_list_size: Option<u32>,
+    /// `SETTINGS_INITIAL_WINDOW_SIZE` for the flow control of each stream
+    pub initial_stream_window_size: Option<u32>,
+    /// The flow control window of the whole connection
+    pub initial_connection_window_size: Option<u32>,
 }
 
 impl Default for ClientConfig {
@@ -79,11 +315,33 @@ impl Default for ClientConfig {
         Self {
             endpoint: Endpoint::Production,
             request_timeout_secs: Some(DEFAULT_REQUEST_TIMEOUT_SECS),
+            connect_timeout_secs: None,
+            resolve_override: None,
+            pinned_spki: None,
+            #[cfg(any(test, feature = "test-util"))]
+            plaintext_h2: false,
             pool_idle_timeout_secs: None,
+            idle_timeout: None,
             // Send HTTP/2 PING every 1 hour as per: https://developer.apple.com/documentation/usernotifications/sending-notification-requests-to-apns#Follow-best-practices-while-sending-push-notifications-with-APNs
             // Reuse a connection as long as possible. In most cases, you can reuse a connection for many hours to days. If your connection is mostly idle, you may send a HTTP2 PING frame after an hour of inactivity. Reusing a connection often results in less bandwidth and CPU consumption.
             http2_keep_alive_interval_secs: Some(60 * 60),
             http2_keep_alive_while_idle: true,
+            max_inflight: None,
+            clock: Arc::new(SystemClock),
+            jwt_header: serde_json::Map::new(),
+            default_options: NotificationOptions::default(),
+            topic_for: HashMap::new(),
+            strict_push_type_inference: false,
+            revalidate_push_type: true,
+            dedup_window: None,
+            max_payload_bytes: HashMap::new(),
+            h2_settings: H2Settings::default(),
+            adaptive_window: false,
+            apns_id_generator: None,
+            minimize_payload: false,
+            on_serialize: None,
+            on_invalid_token: None,
+            retry_policy: None,
         }
     }
 }
@@ -95,16 +353,62 @@ impl ClientConfig {
             ..Default::default()
         }
     }
+
+    /// Whether to connect without TLS, see [`ClientConfig::plaintext_h2`]
+    fn plaintext_h2(&self) -> bool {
+        #[cfg(any(test, feature = "test-util"))]
+        return self.plaintext_h2;
+
+        #[cfg(not(any(test, feature = "test-util")))]
+        false
+    }
+}
+
+/// The credentials for token based authentication, as provisioned in your
+/// [Apple developer account](https://developer.apple.com/account/).
+#[derive(Clone)]
+pub struct TokenAuth {
+    /// The ID of the signing key
+    pub key_id: String,
+    /// The ID of the team the key belongs to
+    pub team_id: String,
+    /// The private key in PKCS#8 PEM format
+    pub key: Vec<u8>,
+}
+
+impl fmt::Debug for TokenAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenAuth")
+            .field("key_id", &self.key_id)
+            .field("team_id", &self.team_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The server at the other end of a connection to APNs, see
+/// [`Client::peer_info`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerInfo {
+    /// The resolved address of the server
+    pub addr: SocketAddr,
+    /// The subjects of the certificate chain presented by the server, leaf
+    /// first
+    pub cert_chain_summary: Vec<String>,
 }
 
 #[derive(Debug, Clone, Default)]
 struct ClientBuilder {
     config: ClientConfig,
     signer: Option<Signer>,
+    additional_signers: Vec<Signer>,
     connector: Option<HyperConnector>,
+    connections: Arc<ConnectionTracker>,
 }
 
 impl ClientBuilder {
+    /// Connect with `connector` instead of the default one. It has to verify
+    /// certificates with a [`RecordingVerifier`] sharing the `connections` of
+    /// the builder, or [`ClientConfig::pinned_spki`] goes unchecked.
     fn connector(mut self, connector: HyperConnector) -> Self {
         self.connector = Some(connector);
         self
@@ -115,42 +419,112 @@ impl ClientBuilder {
         self
     }
 
+    fn additional_signers(mut self, signers: Vec<Signer>) -> Self {
+        self.additional_signers = signers;
+        self
+    }
+
     fn config(mut self, config: ClientConfig) -> Self {
+        self.connections = Arc::new(ConnectionTracker::new(config.pinned_spki.clone()));
         self.config = config;
         self
     }
 
     fn build(self) -> Result<Client, Error> {
+        let plaintext_h2 = self.config.plaintext_h2();
         let ClientBuilder {
             config:
                 ClientConfig {
                     endpoint,
                     request_timeout_secs,
+                    connect_timeout_secs,
+                    resolve_override,
+                    pinned_spki: _,
+                    #[cfg(any(test, feature = "test-util"))]
+                        plaintext_h2: _,
                     pool_idle_timeout_secs,
+                    idle_timeout,
                     http2_keep_alive_interval_secs,
                     http2_keep_alive_while_idle,
+                    max_inflight,
+                    clock,
+                    jwt_header,
+                    default_options,
+                    topic_for,
+                    strict_push_type_inference,
+                    revalidate_push_type,
+                    dedup_window,
+                    max_payload_bytes,
+                    h2_settings,
+                    adaptive_window,
+                    apns_id_generator,
+                    minimize_payload,
+                    on_serialize,
+                    on_invalid_token,
+                    retry_policy,
                 },
             signer,
+            additional_signers,
             connector,
+            connections,
         } = self;
 
+        let signer = signer
+            .map(|signer| signer.with_header_fields(jwt_header.clone()))
+            .transpose()?;
+        let additional_signers = additional_signers
+            .into_iter()
+            .map(|signer| signer.with_header_fields(jwt_header.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+
         let connector = if let Some(connector) = connector {
             connector
         } else {
-            default_connector()?
+            default_connector(&connections, connect_timeout_secs, resolve_override, plaintext_h2)?
         };
 
-        let http_client = HttpClient::builder(TokioExecutor::new())
-            .pool_idle_timeout(pool_idle_timeout_secs.map(Duration::from_secs))
+        let mut builder = HttpClient::builder(TokioExecutor::new());
+
+        builder
+            .pool_idle_timeout(idle_timeout.or(pool_idle_timeout_secs.map(Duration::from_secs)))
+            .pool_timer(TokioTimer::new())
             .http2_only(true)
             .http2_keep_alive_interval(http2_keep_alive_interval_secs.map(Duration::from_secs))
             .http2_keep_alive_while_idle(http2_keep_alive_while_idle)
-            .timer(TokioTimer::new())
-            .build(connector);
+            .http2_header_table_size(h2_settings.header_table_size)
+            .http2_max_frame_size(h2_settings.max_frame_size)
+            .http2_initial_stream_window_size(h2_settings.initial_stream_window_size)
+            .http2_initial_connection_window_size(h2_settings.initial_connection_window_size)
+            .http2_adaptive_window(adaptive_window)
+            .timer(TokioTimer::new());
+
+        if let Some(max_header_list_size) = h2_settings.max_header_list_size {
+            builder.http2_max_header_list_size(max_header_list_size);
+        }
+
+        let http_client = builder.build(connector);
 
         Ok(Client {
             http_client,
-            options: ConnectionOptions::new(endpoint, signer, request_timeout_secs),
+            options: ConnectionOptions {
+                inflight: max_inflight.map(|permits| Arc::new(Semaphore::new(permits))),
+                clock,
+                default_options,
+                topic_for: Arc::new(topic_for),
+                strict_push_type_inference,
+                revalidate_push_type,
+                dedup: dedup_window.map(|window| Arc::new(DedupCache::new(window))),
+                max_payload_bytes: Arc::new(max_payload_bytes),
+                additional_signers,
+                minimize_payload,
+                on_serialize: on_serialize.map(SerializeHook),
+                on_invalid_token: on_invalid_token.map(InvalidTokenHook),
+                retry_policy,
+                apns_id_generator,
+                connections,
+                scheme: if plaintext_h2 { "http" } else { "https" },
+                ..ConnectionOptions::new(endpoint, signer, request_timeout_secs)
+            },
         })
     }
 }
@@ -158,8 +532,25 @@ impl ClientBuilder {
 #[derive(Debug, Clone)]
 struct ConnectionOptions {
     endpoint: Endpoint,
+    scheme: &'static str,
     request_timeout: Duration,
     signer: Option<Signer>,
+    additional_signers: Vec<Signer>,
+    inflight: Option<Arc<Semaphore>>,
+    clock: Arc<dyn Clock>,
+    default_options: NotificationOptions<'static>,
+    topic_for: Arc<HashMap<PushType, String>>,
+    strict_push_type_inference: bool,
+    revalidate_push_type: bool,
+    dedup: Option<Arc<DedupCache>>,
+    max_payload_bytes: Arc<HashMap<PushType, usize>>,
+    client_cert_not_after: Option<OffsetDateTime>,
+    minimize_payload: bool,
+    on_serialize: Option<SerializeHook>,
+    on_invalid_token: Option<InvalidTokenHook>,
+    retry_policy: Option<Arc<dyn RetryPolicy>>,
+    apns_id_generator: Option<Arc<dyn IdGenerator>>,
+    connections: Arc<ConnectionTracker>,
 }
 
 impl ConnectionOptions {
@@ -167,12 +558,36 @@ impl ConnectionOptions {
         let request_timeout = Duration::from_secs(request_timeout_secs.unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS));
         Self {
             endpoint,
+            scheme: "https",
             request_timeout,
             signer,
+            additional_signers: Vec::new(),
+            inflight: None,
+            clock: Arc::new(SystemClock),
+            default_options: NotificationOptions::default(),
+            topic_for: Arc::default(),
+            strict_push_type_inference: false,
+            revalidate_push_type: true,
+            dedup: None,
+            max_payload_bytes: Arc::default(),
+            client_cert_not_after: None,
+            minimize_payload: false,
+            on_serialize: None,
+            on_invalid_token: None,
+            retry_policy: None,
+            apns_id_generator: None,
+            connections: Arc::default(),
         }
     }
 }
 
+/// A notification request ready to be sent
+struct Prepared {
+    request: hyper::Request<BoxBody<Bytes, Infallible>>,
+    delivery: Delivery,
+    dedup_key: Option<u64>,
+}
+
 impl Client {
     /// Creates a builder for the [`Client`] that uses the default connector and
     /// [`Endpoint::Production`]
@@ -180,29 +595,13 @@ impl Client {
         ClientBuilder::default()
     }
 
-    /// Create a connection to APNs using a PKCS#12 provider certificate (PFX/.p12).
+    /// Create a connection to APNs using a PKCS#12 provider certificate
+    /// (PFX/.p12), or a PEM bundle of the certificate and key, see
+    /// [`load_identity`].
     pub fn certificate<R>(certificate: &mut R, password: &str, config: ClientConfig) -> Result<Client, Error>
     where
         R: Read,
     {
-        #[cfg(feature = "ring")]
-        fn create_connector(certificate_bytes: &[u8], password: &str) -> Result<HttpsConnector<HttpConnector>, Error> {
-            // Parse the PKCS#12 archive into PEM-encoded certificate chain and private key
-            let (cert_pem, key_pem) = crate::pkcs12::parse_pkcs12(certificate_bytes, password)?;
-            // Build a TLS connector using the parsed certificate and key PEM blocks
-
-            client_cert_connector(&cert_pem, &key_pem)
-        }
-
-        #[cfg(all(not(feature = "ring"), feature = "openssl"))]
-        fn create_connector(certificate_bytes: &[u8], password: &str) -> Result<HttpsConnector<HttpConnector>, Error> {
-            let pkcs = openssl::pkcs12::Pkcs12::from_der(certificate_bytes)?.parse2(password)?;
-            let Some((cert, pkey)) = pkcs.cert.zip(pkcs.pkey) else {
-                return Err(Error::InvalidCertificate);
-            };
-            client_cert_connector(&cert.to_pem()?, &pkey.private_key_to_pem_pkcs8()?)
-        }
-
         // Load all bytes from the certificate reader
         let certificate_bytes = {
             let mut data = Vec::<u8>::new();
@@ -210,17 +609,72 @@ impl Client {
             data
         };
 
-        let connector = create_connector(certificate_bytes.as_ref(), password)?;
-        Self::builder().connector(connector).config(config).build()
+        let (cert_pem, key_pem) = load_identity(&certificate_bytes, Some(password))?;
+
+        Self::certificate_parts(&cert_pem, &key_pem, config)
     }
 
     /// Create a connection to APNs using the raw PEM-formatted certificate and
     /// key, extracted from the provider client certificate you obtain from your
     /// [Apple developer account](https://developer.apple.com/account/)
     pub fn certificate_parts(cert_pem: &[u8], key_pem: &[u8], config: ClientConfig) -> Result<Client, Error> {
-        let connector = client_cert_connector(cert_pem, key_pem)?;
+        let builder = Self::builder().config(config);
+        let connector = client_cert_connector(
+            cert_pem,
+            key_pem,
+            &builder.connections,
+            builder.config.connect_timeout_secs,
+            builder.config.resolve_override,
+            builder.config.plaintext_h2(),
+        )?;
+        let not_after = client_cert_not_after(cert_pem)?;
+
+        let mut client = builder.connector(connector).build()?;
+        client.options.client_cert_not_after = Some(not_after);
+
+        Ok(client)
+    }
+
+    /// The expiry of the client certificate, for clients created with
+    /// [`certificate`](Self::certificate) or
+    /// [`certificate_parts`](Self::certificate_parts).
+    pub fn client_cert_not_after(&self) -> Option<OffsetDateTime> {
+        self.options.client_cert_not_after
+    }
+
+    /// The server of the connection used by the last notification, for audit
+    /// logging. `None` until a notification was sent.
+    pub fn peer_info(&self) -> Option<PeerInfo> {
+        self.options.connections.peer_info(self.options.endpoint.host())
+    }
+
+    /// The JWT header of the provider tokens signed with the key `key_id`, or
+    /// the default key if `None`, including the fields of
+    /// [`ClientConfig::jwt_header`]. `None` without token based
+    /// authentication.
+    ///
+    /// ```no_run
+    /// # use apns_h2::{Client, ClientConfig};
+    /// # use std::fs::File;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::token(File::open("/path/to/private_key.p8")?, "KEY_ID", "TEAM_ID", ClientConfig::default())?;
+    /// let header = client.jwt_header(None)?.unwrap();
+    ///
+    /// assert_eq!("ES256", header["alg"]);
+    /// assert_eq!("KEY_ID", header["kid"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn jwt_header(
+        &self,
+        key_id: Option<&str>,
+    ) -> Result<Option<serde_json::Map<String, serde_json::Value>>, Error> {
+        Ok(self.signer(key_id)?.map(Signer::header))
+    }
 
-        Self::builder().config(config).connector(connector).build()
+    /// The environment notifications are sent to, from [`ClientConfig::endpoint`].
+    pub fn endpoint(&self) -> &Endpoint {
+        &self.options.endpoint
     }
 
     /// Create a connection to APNs using system certificates, signing every
@@ -234,24 +688,541 @@ impl Client {
         R: Read,
     {
         let signature_ttl = Duration::from_secs(60 * 55);
-        let signer = Signer::new(pkcs8_pem, key_id, team_id, signature_ttl)?;
+        let signer = Signer::new(pkcs8_pem, key_id, team_id, signature_ttl, config.clock.clone())?;
 
         Self::builder().config(config).signer(signer).build()
     }
 
+    /// Create a connection to APNs using system certificates, signing every
+    /// request with the given [`TokenAuth`] credentials.
+    ///
+    /// ```no_run
+    /// # use apns_h2::{Client, ClientConfig, TokenAuth};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::with_token_auth(
+    ///     TokenAuth {
+    ///         key_id: String::from("KEY_ID"),
+    ///         team_id: String::from("TEAM_ID"),
+    ///         key: std::fs::read("/path/to/private_key.p8")?,
+    ///     },
+    ///     ClientConfig::default(),
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_token_auth(auth: TokenAuth, config: ClientConfig) -> Result<Client, Error> {
+        Self::token(auth.key.as_slice(), auth.key_id, auth.team_id, config)
+    }
+
+    /// Create a connection to APNs using system certificates, able to sign
+    /// requests with any of the given keys, e.g. during a key rollover.
+    ///
+    /// The key is selected per notification with
+    /// [`NotificationOptions::signing_key_id`], defaulting to the first key.
+    pub fn with_token_auths(auths: Vec<TokenAuth>, config: ClientConfig) -> Result<Client, Error> {
+        let signature_ttl = Duration::from_secs(60 * 55);
+
+        let mut signers = auths
+            .into_iter()
+            .map(|auth| {
+                Signer::new(
+                    auth.key.as_slice(),
+                    auth.key_id,
+                    auth.team_id,
+                    signature_ttl,
+                    config.clock.clone(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if signers.is_empty() {
+            return Err(Error::InvalidOptions(String::from(
+                "At least one signing key is required.",
+            )));
+        }
+
+        let signer = signers.remove(0);
+
+        Self::builder()
+            .config(config)
+            .signer(signer)
+            .additional_signers(signers)
+            .build()
+    }
+
     /// Send a notification payload.
     ///
     /// See [ErrorReason](enum.ErrorReason.html) for possible errors.
     #[cfg_attr(feature = "tracing", ::tracing::instrument)]
     pub async fn send<T: PayloadLike>(&self, payload: T) -> Result<Response, Error> {
-        let request = self.build_request(payload)?;
+        self.send_renewing_token(payload, None, None).await.1
+    }
+
+    /// Send a notification payload and return an [`AuditRecord`] of what was
+    /// sent along with the response, e.g. for compliance logs. A failed send
+    /// returns the record along with the error, with no
+    /// [`attempts`](AuditRecord::attempts) if the request was never sent.
+    ///
+    /// See [ErrorReason](enum.ErrorReason.html) for possible errors.
+    #[cfg_attr(feature = "tracing", ::tracing::instrument)]
+    pub async fn send_audited<T: PayloadLike>(
+        &self,
+        payload: T,
+    ) -> Result<(Response, AuditRecord), (Error, AuditRecord)> {
+        let mut audit = AuditRecord::new(payload.get_target().as_str());
+
+        match self.send_renewing_token(payload, None, Some(&mut audit)).await.1 {
+            Ok(response) => Ok((response, audit)),
+            Err(e) => Err((e, audit)),
+        }
+    }
+
+    /// Send a notification payload with the given headers instead of the
+    /// ones derived from its [`NotificationOptions`], e.g. as returned by
+    /// [`NotificationBuilder::build_request`](crate::NotificationBuilder::build_request).
+    ///
+    /// See [ErrorReason](enum.ErrorReason.html) for possible errors.
+    #[cfg_attr(feature = "tracing", ::tracing::instrument)]
+    pub async fn send_with_headers<T: PayloadLike>(
+        &self,
+        payload: T,
+        headers: RequestHeaders,
+    ) -> Result<Response, Error> {
+        self.send_renewing_token(payload, Some(headers), None).await.1
+    }
+
+    /// Send a notification payload, handing the payload back if sending
+    /// fails, e.g. to queue it for a later retry.
+    ///
+    /// See [ErrorReason](enum.ErrorReason.html) for possible errors.
+    #[cfg_attr(feature = "tracing", ::tracing::instrument)]
+    pub async fn send_owned<T: PayloadLike>(&self, payload: T) -> Result<Response, (T, Error)> {
+        match self.send_renewing_token(payload, None, None).await {
+            (_, Ok(response)) => Ok(response),
+            (payload, Err(e)) => Err((payload, e)),
+        }
+    }
+
+    /// Sends the payload, re-signing the authentication token and sending
+    /// once more if APNs rejects the token as expired. Failed sends are
+    /// retried as the [`ClientConfig::retry_policy`] decides. Every request
+    /// sent is recorded in `audit`.
+    async fn send_renewing_token<T: PayloadLike>(
+        &self,
+        payload: T,
+        headers: Option<RequestHeaders>,
+        mut audit: Option<&mut AuditRecord>,
+    ) -> (T, Result<Response, Error>) {
+        let started = Instant::now();
+        let mut attempt = 0;
+
+        payload.mark_sent();
+
+        let result = loop {
+            let mut result = match self.prepare_audited(&payload, headers.clone(), audit.as_deref_mut()) {
+                Ok(prepared) => self.send_prepared(prepared).await,
+                Err(e) => Err(e),
+            };
+
+            if is_expired_provider_token(&result) {
+                result = match self.renew_rejected_token(&payload) {
+                    Ok(true) => match self.prepare_audited(&payload, headers.clone(), audit.as_deref_mut()) {
+                        Ok(prepared) => self.send_prepared(prepared).await,
+                        Err(e) => Err(e),
+                    },
+                    Ok(false) => result,
+                    Err(e) => Err(e),
+                };
+            }
+
+            let (Some(retry_policy), Err(e)) = (&self.options.retry_policy, &result) else {
+                break result;
+            };
+
+            attempt += 1;
+
+            match retry_policy.next_delay(attempt, e).await {
+                Some(delay) => sleep(delay).await,
+                None => break result,
+            }
+        };
+
+        self.report_invalid_token(&payload, &result);
+
+        if let Some(audit) = audit {
+            let response = match &result {
+                Ok(response) => Some(response),
+                Err(e) => e.response(),
+            };
+
+            if let Some(apns_id) = response.and_then(|response| response.apns_id.clone()) {
+                audit.apns_id = Some(apns_id);
+            }
+
+            audit.duration = started.elapsed();
+        }
+
+        (payload, result)
+    }
+
+    /// Like [`prepare`](Self::prepare), recording the request in `audit`.
+    fn prepare_audited<T: PayloadLike>(
+        &self,
+        payload: &T,
+        headers: Option<RequestHeaders>,
+        audit: Option<&mut AuditRecord>,
+    ) -> Result<Prepared, Error> {
+        let prepared = self.prepare(payload, headers)?;
+
+        if let Some(audit) = audit {
+            audit.record_request(&prepared.request);
+        }
+
+        Ok(prepared)
+    }
+
+    /// Calls [`ClientConfig::on_invalid_token`] if APNs rejected the device
+    /// token of the payload.
+    fn report_invalid_token<T: PayloadLike>(&self, payload: &T, result: &Result<Response, Error>) {
+        let (Some(InvalidTokenHook(hook)), Target::Device(token), Some(timestamp)) = (
+            &self.options.on_invalid_token,
+            payload.get_target(),
+            rejected_token_timestamp(result),
+        ) else {
+            return;
+        };
+
+        hook(token, timestamp);
+    }
+
+    /// Renews the token of the signer used for the payload. Returns whether
+    /// a new token was issued.
+    fn renew_rejected_token<T: PayloadLike>(&self, payload: &T) -> Result<bool, Error> {
+        let options = self.effective_options(payload.get_options());
+
+        match self.signer(options.signing_key_id)? {
+            Some(signer) => signer.renew_rejected(),
+            None => Ok(false),
+        }
+    }
+
+    /// Builds the request for a payload, reserving it in the
+    /// [`ClientConfig::dedup_window`]. Fails with [`Error::Duplicate`] if the
+    /// same notification is being sent or was sent within the window.
+    fn prepare<T: PayloadLike>(&self, payload: &T, headers: Option<RequestHeaders>) -> Result<Prepared, Error> {
+        let dedup_key = match self.options.dedup {
+            Some(ref dedup) => {
+                let key = self.dedup_key(payload)?;
+
+                if !dedup.reserve(key, self.options.clock.now()) {
+                    return Err(Error::Duplicate);
+                }
+
+                Some(key)
+            }
+            None => None,
+        };
+
+        let delivery = Delivery::from_expiration(self.effective_options(payload.get_options()).apns_expiration);
+        let request = self.build_request_with_headers(payload, headers).inspect_err(|_| {
+            if let (Some(dedup), Some(key)) = (&self.options.dedup, dedup_key) {
+                dedup.release(key);
+            }
+        })?;
+
+        Ok(Prepared {
+            request,
+            delivery,
+            dedup_key,
+        })
+    }
+
+    async fn send_prepared(&self, prepared: Prepared) -> Result<Response, Error> {
+        let Prepared {
+            request,
+            delivery,
+            dedup_key,
+        } = prepared;
+
+        let result = self.send_request(request, delivery).await;
+
+        if let (Some(dedup), Some(key)) = (&self.options.dedup, dedup_key) {
+            match result {
+                Ok(_) => dedup.confirm(key, self.options.clock.now()),
+                Err(_) => dedup.release(key),
+            }
+        }
+
+        result
+    }
+
+    /// The key identifying the notification in [`ClientConfig::dedup_window`]
+    fn dedup_key<T: PayloadLike>(&self, payload: &T) -> Result<u64, Error> {
+        let options = self.effective_options(payload.get_options());
+
+        Ok(DedupCache::key(
+            payload.get_target().as_str(),
+            options.apns_collapse_id.as_ref().map(|collapse_id| collapse_id.value),
+            &payload.to_json_string()?,
+        ))
+    }
+
+    /// Send a notification payload and return the status, headers and body
+    /// exactly as received from APNs, for debugging.
+    ///
+    /// Unlike [`send`](Self::send), an error status from APNs is not turned
+    /// into an [`Error`] and the
+    /// [`ClientConfig::dedup_window`] is not applied.
+    #[cfg_attr(feature = "tracing", ::tracing::instrument)]
+    pub async fn send_verbose<T: PayloadLike>(&self, payload: T) -> Result<VerboseResponse, Error> {
+        payload.mark_sent();
+        let request = self.build_request(&payload)?;
+
+        let _permit = self.acquire_inflight_permit().await;
+        let requesting = self.http_client.request(request);
+
+        let Ok(response_result) = timeout(self.options.request_timeout, requesting).await else {
+            return Err(Error::RequestTimeout(self.options.request_timeout.as_secs()));
+        };
+
+        let (parts, body) = response_result.map_err(connection_error)?.into_parts();
+        let body = body.collect().await?.to_bytes();
+
+        Ok(VerboseResponse {
+            status: parts.status,
+            headers: parts.headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+
+    /// Send a request assembled elsewhere, e.g. by a gateway forwarding
+    /// notifications, to the device `token`.
+    ///
+    /// The `headers` and `body` are sent as given. Only the `authorization`
+    /// header, the `apns-topic` of the [`ClientConfig::default_options`] and
+    /// the `content-type` are added if missing. The body is not validated.
+    ///
+    /// See [ErrorReason](enum.ErrorReason.html) for possible errors.
+    #[cfg_attr(feature = "tracing", ::tracing::instrument(skip(body)))]
+    pub async fn send_http(&self, headers: HeaderMap, body: Bytes, token: &str) -> Result<Response, Error> {
+        let delivery = Delivery::from_expiration(
+            headers
+                .get("apns-expiration")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok()),
+        );
+        let request = self.build_http_request(headers, body, token)?;
+
+        self.send_request(request, delivery).await
+    }
+
+    /// Send notification payloads one after another in a background task,
+    /// yielding the results in the same order through the returned channel
+    /// receiver, e.g. to wrap in a `tokio_stream::wrappers::ReceiverStream`
+    /// for a `Stream`.
+    ///
+    /// At most `buffer` results are held in the channel, at least one. When
+    /// it is full, sending pauses until the receiver catches up, so a slow
+    /// consumer throttles the sender instead of results piling up in memory.
+    /// Dropping the receiver stops sending the remaining payloads.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn send_all<T, I>(&self, payloads: I, buffer: usize) -> mpsc::Receiver<Result<Response, Error>>
+    where
+        T: PayloadLike + Send + 'static,
+        I: IntoIterator<Item = T>,
+        I::IntoIter: Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(buffer.max(1));
+        let client = self.clone();
+        let payloads = payloads.into_iter();
+
+        tokio::spawn(async move {
+            for payload in payloads {
+                let result = client.send(payload).await;
+
+                if tx.send(result).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        rx
+    }
+
+    /// Send the `payload` to every device token in `tokens` with the
+    /// `options`, retrying failures as the `retry_policy` decides, and count
+    /// the outcomes.
+    ///
+    /// Tokens APNs reports as invalid are passed to
+    /// [`ClientConfig::on_invalid_token`], e.g. to purge them from storage.
+    /// The notifications are sent one after another, clones of the client can
+    /// run campaigns for parts of the tokens in parallel.
+    ///
+    /// ```no_run
+    /// # use apns_h2::{Client, ClientConfig, DefaultNotificationBuilder, NotificationBuilder, NotificationOptions};
+    /// # use apns_h2::retry::ExponentialBackoff;
+    /// # use std::sync::Arc;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// # let client = Client::certificate(&mut std::fs::File::open("/path/to/cert_db.p12")?, "", ClientConfig::default())?;
+    /// let payload = DefaultNotificationBuilder::new().body("50% off today").build("", Default::default());
+    /// let options = NotificationOptions {
+    ///     apns_topic: Some("com.example.app"),
+    ///     ..Default::default()
+    /// };
+    /// let tokens = ["token1", "token2"];
+    ///
+    /// let report = client
+    ///     .run_campaign(payload, tokens, options, Arc::new(ExponentialBackoff::default()))
+    ///     .await;
+    ///
+    /// println!("{} delivered, {} failed, {} purged", report.delivered, report.failed, report.purged);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_campaign<'a, I>(
+        &self,
+        payload: Payload<'a>,
+        tokens: I,
+        options: NotificationOptions<'a>,
+        retry_policy: Arc<dyn RetryPolicy>,
+    ) -> CampaignReport
+    where
+        I: IntoIterator,
+        I::Item: Into<Target<'a>>,
+    {
+        let mut client = self.clone();
+        client.options.retry_policy = Some(retry_policy);
+
+        let mut report = CampaignReport::default();
+
+        // The payload is only a template for the notifications
+        payload.mark_sent();
+
+        for token in tokens {
+            let notification = Payload {
+                target: token.into(),
+                options: options.clone(),
+                ..payload.clone()
+            };
+            let result = client.send(notification).await;
+
+            if result.is_ok() {
+                report.delivered += 1;
+            } else if rejected_token_timestamp(&result).is_some() {
+                report.purged += 1;
+            } else {
+                report.failed += 1;
+            }
+        }
+
+        report
+    }
+
+    /// Updates a Live Activity with `content_state`, sending to a push token
+    /// or a broadcast channel depending on `target`. The update is
+    /// timestamped with the client's [`Clock`] and sent as
+    /// [`PushType::LiveActivity`] unless the options set another push type.
+    ///
+    /// Updates to a channel need the bundle ID as `apns_topic`, e.g. from
+    /// [`ClientConfig::default_options`].
+    ///
+    /// ```no_run
+    /// # use apns_h2::{Client, ClientConfig, NotificationOptions};
+    /// # use apns_h2::request::target::ChannelId;
+    /// # use serde_json::json;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// # let client = Client::certificate(&mut std::fs::File::open("/path/to/cert_db.p12")?, "", ClientConfig::default())?;
+    /// let content_state = json!({"score": "2:1"});
+    ///
+    /// client
+    ///     .update_live_activity(
+    ///         "activity-push-token",
+    ///         &content_state,
+    ///         NotificationOptions {
+    ///             apns_topic: Some("com.example.app.push-type.liveactivity"),
+    ///             ..Default::default()
+    ///         },
+    ///     )
+    ///     .await?;
+    ///
+    /// client
+    ///     .update_live_activity(
+    ///         ChannelId::new("dHN0LXNyY2gtY2hubA==")?,
+    ///         &content_state,
+    ///         NotificationOptions {
+    ///             apns_topic: Some("com.example.app"),
+    ///             ..Default::default()
+    ///         },
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn update_live_activity<'a>(
+        &self,
+        target: impl Into<Target<'a>>,
+        content_state: &serde_json::Value,
+        options: NotificationOptions<'a>,
+    ) -> Result<Response, Error> {
+        self.send(self.live_activity_update(target, content_state, options))
+            .await
+    }
+
+    /// The payload sent by [`update_live_activity`](Self::update_live_activity).
+    fn live_activity_update<'a>(
+        &self,
+        target: impl Into<Target<'a>>,
+        content_state: &serde_json::Value,
+        options: NotificationOptions<'a>,
+    ) -> Payload<'a> {
+        let timestamp = self
+            .options
+            .clock
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_secs())
+            .unwrap_or_default();
+        let options = NotificationOptions {
+            apns_push_type: options.apns_push_type.or(Some(PushType::LiveActivity)),
+            ..options
+        };
+
+        DefaultNotificationBuilder::new()
+            .update_activity()
+            .content_state(content_state)
+            .timestamp(timestamp)
+            .build(target, options)
+    }
+
+    async fn send_request(
+        &self,
+        request: hyper::Request<BoxBody<Bytes, Infallible>>,
+        delivery: Delivery,
+    ) -> Result<Response, Error> {
+        let host = request.uri().host().map(ToString::to_string).unwrap_or_default();
+        let sent_apns_id = request
+            .headers()
+            .get("apns-id")
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+
+        let _permit = self.acquire_inflight_permit().await;
         let requesting = self.http_client.request(request);
 
         let Ok(response_result) = timeout(self.options.request_timeout, requesting).await else {
             return Err(Error::RequestTimeout(self.options.request_timeout.as_secs()));
         };
 
-        let response = response_result?;
+        let response = response_result.map_err(connection_error)?;
+
+        let addrs = response
+            .extensions()
+            .get::<HttpInfo>()
+            .map(|info| (info.local_addr(), info.remote_addr()));
+        let reconnected = self.options.connections.reconnected(&host, addrs);
 
         let header_map = response.headers();
 
@@ -261,6 +1232,17 @@ impl Client {
 
         let apns_id = get_header_key_opt(header_map, "apns-id");
 
+        if is_apns_id_mismatch(sent_apns_id.as_deref(), apns_id.as_deref()) {
+            #[cfg(feature = "tracing")]
+            {
+                tracing::warn!(
+                    "APNs answered with the apns-id {:?} to the notification sent with the apns-id {:?}",
+                    apns_id,
+                    sent_apns_id,
+                );
+            }
+        }
+
         let apns_unique_id = if matches!(self.options.endpoint, Endpoint::Sandbox) {
             get_header_key_opt(header_map, "apns-unique-id")
         } else {
@@ -273,63 +1255,351 @@ impl Client {
                 apns_unique_id,
                 error: None,
                 code: response.status().as_u16(),
+                delivery: Some(delivery),
+                reconnected,
             }),
             status => {
                 let body = response.into_body().collect().await?;
 
-                Err(ResponseError(Response {
+                Err(Error::from_response(Response {
                     apns_id,
                     apns_unique_id,
                     error: serde_json::from_slice(&body.to_bytes()).ok(),
                     code: status.as_u16(),
+                    delivery: None,
+                    reconnected,
                 }))
             }
         }
     }
 
-    fn build_request<T: PayloadLike>(&self, payload: T) -> Result<hyper::Request<BoxBody<Bytes, Infallible>>, Error> {
-        let path = format!(
-            "https://{}/3/device/{}",
-            self.options.endpoint,
-            payload.get_device_token()
-        );
+    /// Checks that APNs answers over the client's connection without sending
+    /// a notification, opening the connection if needed.
+    ///
+    /// This is not an HTTP/2 PING, which hyper doesn't expose. It sends a
+    /// `GET` request on a stream of its own, which APNs answers with
+    /// `MethodNotAllowed`, so it counts as a request towards APNs like a
+    /// notification does. Any answer counts as success, only connection
+    /// errors and timeouts fail. Idle connections are already kept alive with
+    /// PING frames as configured with
+    /// [`ClientConfig::http2_keep_alive_interval_secs`].
+    #[cfg_attr(feature = "tracing", ::tracing::instrument)]
+    pub async fn probe(&self) -> Result<(), Error> {
+        let request = self.build_probe_request()?;
+        let requesting = self.http_client.request(request);
 
-        let mut builder = hyper::Request::builder()
-            .uri(&path)
-            .method("POST")
-            .header(CONTENT_TYPE, "application/json");
+        let Ok(response_result) = timeout(self.options.request_timeout, requesting).await else {
+            return Err(Error::RequestTimeout(self.options.request_timeout.as_secs()));
+        };
 
-        let options = payload.get_options();
-        if let Some(ref apns_priority) = options.apns_priority {
-            builder = builder.header("apns-priority", apns_priority.to_string().as_bytes());
-        }
-        if let Some(apns_id) = options.apns_id {
-            builder = builder.header("apns-id", apns_id.as_bytes());
-        }
-        if let Some(apns_push_type) = options.apns_push_type.as_ref() {
-            builder = builder.header("apns-push-type", apns_push_type.to_string().as_bytes());
-        }
-        if let Some(ref apns_expiration) = options.apns_expiration {
-            builder = builder.header("apns-expiration", apns_expiration.to_string().as_bytes());
-        }
-        if let Some(ref apns_collapse_id) = options.apns_collapse_id {
-            builder = builder.header("apns-collapse-id", apns_collapse_id.value.as_bytes());
-        }
-        if let Some(apns_topic) = options.apns_topic {
-            builder = builder.header("apns-topic", apns_topic.as_bytes());
-        }
-        if let Some(ref signer) = self.options.signer {
-            let auth = signer.with_signature(|signature| format!("Bearer {}", signature))?;
+        response_result.map_err(connection_error)?;
 
-            builder = builder.header(AUTHORIZATION, auth.as_bytes());
-        }
+        Ok(())
+    }
+
+    fn build_probe_request(&self) -> Result<hyper::Request<BoxBody<Bytes, Infallible>>, Error> {
+        hyper::Request::builder()
+            .uri(format!("{}://{}/3/device/", self.options.scheme, self.options.endpoint))
+            .method("GET")
+            .body(Empty::new().boxed())
+            .map_err(Error::BuildRequestError)
+    }
+
+    /// Create, read or delete a broadcast channel of the app with the given
+    /// bundle ID. The returned [`Channel::channel_id`] can be used as a
+    /// [`Target::Channel`] to broadcast Live Activity updates.
+    ///
+    /// See [ErrorReason](enum.ErrorReason.html) for possible errors.
+    #[cfg_attr(feature = "tracing", ::tracing::instrument)]
+    pub async fn manage_channel(&self, bundle_id: &str, operation: ChannelOperation<'_>) -> Result<Channel, Error> {
+        let request = self.build_channel_request(bundle_id, &operation)?;
+
+        let _permit = self.acquire_inflight_permit().await;
+        let requesting = self.http_client.request(request);
+
+        let Ok(response_result) = timeout(self.options.request_timeout, requesting).await else {
+            return Err(Error::RequestTimeout(self.options.request_timeout.as_secs()));
+        };
+
+        let (parts, body) = response_result.map_err(connection_error)?.into_parts();
+        let body = body.collect().await?.to_bytes();
+
+        channel_from_response(operation, parts.status, &parts.headers, &body)
+    }
+
+    fn build_channel_request(
+        &self,
+        bundle_id: &str,
+        operation: &ChannelOperation<'_>,
+    ) -> Result<hyper::Request<BoxBody<Bytes, Infallible>>, Error> {
+        validate_topic(bundle_id)?;
+
+        let path = format!(
+            "{}://{}/1/apps/{}/channels",
+            self.options.scheme,
+            self.options.endpoint.channel_management_host(),
+            bundle_id
+        );
+
+        let (method, channel_id, body) = match operation {
+            ChannelOperation::Create(message_storage_policy) => {
+                let body = serde_json::to_string(&ChannelBody {
+                    message_storage_policy: *message_storage_policy,
+                    push_type: String::from("LiveActivity"),
+                })?;
+
+                ("POST", None, Some(body))
+            }
+            ChannelOperation::Read(channel_id) => ("GET", Some(channel_id), None),
+            ChannelOperation::Delete(channel_id) => ("DELETE", Some(channel_id), None),
+        };
+
+        let mut builder = hyper::Request::builder().uri(&path).method(method);
+
+        if let Some(channel_id) = channel_id {
+            builder = builder.header("apns-channel-id", channel_id.as_str().as_bytes());
+        }
+        if let Some(ref signer) = self.options.signer {
+            let auth = signer.with_signature(|signature| format!("Bearer {}", signature))?;
+
+            builder = builder.header(AUTHORIZATION, auth.as_bytes());
+        }
+
+        match body {
+            Some(body) => builder
+                .header(CONTENT_TYPE, "application/json")
+                .header(CONTENT_LENGTH, format!("{}", body.len()).as_bytes())
+                .body(Full::from(body.into_bytes()).boxed()),
+            None => builder.body(Empty::new().boxed()),
+        }
+        .map_err(Error::BuildRequestError)
+    }
+
+    /// The signer with the given key ID, or the default signer if `None`
+    fn signer(&self, key_id: Option<&str>) -> Result<Option<&Signer>, Error> {
+        let Some(key_id) = key_id else {
+            return Ok(self.options.signer.as_ref());
+        };
+
+        self.options
+            .signer
+            .iter()
+            .chain(&self.options.additional_signers)
+            .find(|signer| signer.key_id() == key_id)
+            .map(Some)
+            .ok_or_else(|| Error::InvalidOptions(format!("No signing key with the ID {}.", key_id)))
+    }
+
+    /// The notification options merged with [`ClientConfig::default_options`]
+    fn effective_options<'b>(&self, options: &NotificationOptions<'b>) -> NotificationOptions<'b> {
+        options.clone().with_defaults(&self.options.default_options)
+    }
+
+    /// Waits until the request may be sent without exceeding
+    /// [`ClientConfig::max_inflight`]. The returned permit must be held until
+    /// the response is received.
+    async fn acquire_inflight_permit(&self) -> Option<SemaphorePermit<'_>> {
+        match self.options.inflight {
+            Some(ref semaphore) => Some(semaphore.acquire().await.expect("The semaphore is never closed")),
+            None => None,
+        }
+    }
+
+    fn build_request<T: PayloadLike>(&self, payload: &T) -> Result<hyper::Request<BoxBody<Bytes, Infallible>>, Error> {
+        self.build_request_with_headers(payload, None)
+    }
+
+    /// Builds the request, using the headers derived from the options unless
+    /// `headers` is given.
+    fn build_request_with_headers<T: PayloadLike>(
+        &self,
+        payload: &T,
+        headers: Option<RequestHeaders>,
+    ) -> Result<hyper::Request<BoxBody<Bytes, Infallible>>, Error> {
+        let mut options = payload.get_options().clone();
+        let topic = options.apns_topic.or(self.options.default_options.apns_topic);
+        let push_type = options
+            .apns_push_type
+            .or_else(|| topic.and_then(PushType::from_topic))
+            .unwrap_or_else(|| self.infer_push_type(payload));
+
+        if options.apns_topic.is_none() {
+            options.apns_topic = self.options.topic_for.get(&push_type).map(String::as_str);
+        }
+
+        let options = self.effective_options(&options);
+        let mut headers = headers.unwrap_or_else(|| RequestHeaders::new(&options, payload));
+
+        if let (None, Some(generator)) = (headers.get("apns-id"), &self.options.apns_id_generator) {
+            headers.insert("apns-id", generator.next_id()?);
+        }
+
+        match headers.get("apns-push-type").map(str::parse::<PushType>) {
+            Some(Ok(push_type)) => {
+                if self.options.revalidate_push_type {
+                    payload.revalidate(push_type)?;
+                }
+            }
+            Some(Err(e)) => return Err(e),
+            // APNs may not deliver a notification without a push type, so the
+            // one required by the topic or guessed from the payload is sent,
+            // but not checked like a requested one
+            None => {
+                headers.insert("apns-push-type", push_type.to_string());
+            }
+        }
+
+        if let Some(apns_topic) = options.apns_topic {
+            validate_topic(apns_topic)?;
+        }
+
+        if let Some(apns_collapse_id) = headers.get("apns-collapse-id") {
+            validate_collapse_id(apns_collapse_id)?;
+        }
+
+        let path = match payload.get_target() {
+            Target::Device(device_token) => format!(
+                "{}://{}/3/device/{}",
+                self.options.scheme, self.options.endpoint, device_token
+            ),
+            Target::Channel(_) => {
+                let Some(apns_topic) = options.apns_topic else {
+                    return Err(Error::InvalidOptions(String::from(
+                        "The apns_topic is required when sending to a channel.",
+                    )));
+                };
+
+                format!(
+                    "{}://{}/4/broadcasts/apps/{}",
+                    self.options.scheme, self.options.endpoint, apns_topic
+                )
+            }
+        };
+
+        let mut builder = hyper::Request::builder()
+            .uri(&path)
+            .method("POST")
+            .header(CONTENT_TYPE, "application/json");
+
+        if let Target::Channel(channel_id) = payload.get_target() {
+            builder = builder.header("apns-channel-id", channel_id.as_bytes());
+        }
+        for (name, value) in headers.iter() {
+            builder = builder.header(name, value.as_bytes());
+        }
+        if let Some(signer) = self.signer(options.signing_key_id)? {
+            let auth = signer.with_signature(|signature| format!("Bearer {}", signature))?;
+
+            builder = builder.header(AUTHORIZATION, auth.as_bytes());
+        }
+
+        let payload_json = if self.options.minimize_payload || self.options.on_serialize.is_some() {
+            let mut value = serde_json::to_value(payload)?;
+
+            if self.options.minimize_payload {
+                minimize_aps(&mut value);
+            }
+            if let Some(SerializeHook(ref hook)) = self.options.on_serialize {
+                hook(&mut value);
+            }
+
+            serde_json::to_string(&value)?
+        } else {
+            payload.to_json_string()?
+        };
+
+        let limit = self.payload_limit(options.apns_push_type.unwrap_or(push_type));
+
+        if payload_json.len() > limit {
+            return Err(Error::PayloadTooLarge {
+                size: payload_json.len(),
+                limit,
+            });
+        }
 
-        let payload_json = payload.to_json_string()?;
         builder = builder.header(CONTENT_LENGTH, format!("{}", payload_json.len()).as_bytes());
 
         let request_body = Full::from(payload_json.into_bytes()).boxed();
         builder.body(request_body).map_err(Error::BuildRequestError)
     }
+
+    /// The push type guessed from the payload, see
+    /// [`ClientConfig::strict_push_type_inference`].
+    fn infer_push_type<T: PayloadLike>(&self, payload: &T) -> PushType {
+        match payload.get_push_type() {
+            PushType::Background if self.options.strict_push_type_inference => PushType::Alert,
+            push_type => push_type,
+        }
+    }
+
+    /// The maximum payload size for the push type, see
+    /// [`ClientConfig::max_payload_bytes`].
+    fn payload_limit(&self, push_type: PushType) -> usize {
+        self.options
+            .max_payload_bytes
+            .get(&push_type)
+            .copied()
+            .unwrap_or_else(|| push_type.max_payload_bytes())
+    }
+
+    /// Builds the request for [`send_http`](Self::send_http), adding the
+    /// headers missing from `headers`.
+    fn build_http_request(
+        &self,
+        mut headers: HeaderMap,
+        body: Bytes,
+        token: &str,
+    ) -> Result<hyper::Request<BoxBody<Bytes, Infallible>>, Error> {
+        if !headers.contains_key("apns-topic") {
+            if let Some(apns_topic) = self.options.default_options.apns_topic {
+                headers.insert(
+                    "apns-topic",
+                    HeaderValue::from_str(apns_topic).map_err(|e| Error::BuildRequestError(e.into()))?,
+                );
+            }
+        }
+
+        if !headers.contains_key(AUTHORIZATION) {
+            if let Some(signer) = self.signer(None)? {
+                let auth = signer.with_signature(|signature| format!("Bearer {}", signature))?;
+
+                headers.insert(
+                    AUTHORIZATION,
+                    HeaderValue::try_from(auth).map_err(|e| Error::BuildRequestError(e.into()))?,
+                );
+            }
+        }
+
+        headers
+            .entry(CONTENT_TYPE)
+            .or_insert(HeaderValue::from_static("application/json"));
+        headers.insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+
+        let mut builder = hyper::Request::builder()
+            .uri(format!(
+                "{}://{}/3/device/{}",
+                self.options.scheme, self.options.endpoint, token
+            ))
+            .method("POST");
+
+        if let Some(request_headers) = builder.headers_mut() {
+            *request_headers = headers;
+        }
+
+        builder.body(Full::from(body).boxed()).map_err(Error::BuildRequestError)
+    }
+}
+
+/// Whether APNs rejected the request because the authentication token is
+/// too old
+fn is_expired_provider_token(result: &Result<Response, Error>) -> bool {
+    matches!(
+        result,
+        Err(Error::Forbidden {
+            reason: Some(ErrorReason::ExpiredProviderToken),
+            ..
+        })
+    )
 }
 
 #[cfg(feature = "ring")]
@@ -347,34 +1617,218 @@ fn default_crypto_provider() -> Arc<rustls::crypto::CryptoProvider> {
     panic!("No provider set");
 }
 
-/// Create a [`rustls::ConfigBuilder`] with the provider preset and platform
-/// verifier enabled
-fn client_config_builder() -> Result<rustls::ConfigBuilder<rustls::ClientConfig, rustls::client::WantsClientCert>, Error>
-{
-    use hyper_rustls::ConfigBuilderExt as _;
-    // Allow setting a runtime default crypto provider, otherwise use the
-    // default.
-    let provider = rustls::crypto::CryptoProvider::get_default()
+/// The runtime default crypto provider if one is set, otherwise the default
+/// of the enabled feature
+pub(crate) fn crypto_provider() -> Arc<rustls::crypto::CryptoProvider> {
+    rustls::crypto::CryptoProvider::get_default()
         .cloned()
-        .unwrap_or_else(default_crypto_provider);
+        .unwrap_or_else(default_crypto_provider)
+}
+
+/// Whether APNs answered with another `apns-id` than the one sent. UUIDs are
+/// compared ignoring case, and a missing ID on either side is no mismatch.
+fn is_apns_id_mismatch(sent: Option<&str>, echoed: Option<&str>) -> bool {
+    match (sent, echoed) {
+        (Some(sent), Some(echoed)) => !sent.eq_ignore_ascii_case(echoed),
+        _ => false,
+    }
+}
+
+/// The channel APNs answered a [`Client::manage_channel`] request with.
+fn channel_from_response(
+    operation: ChannelOperation<'_>,
+    status: StatusCode,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Channel, Error> {
+    let header = |key: &'static str| headers.get(key).and_then(|s| s.to_str().ok()).map(String::from);
+    let apns_request_id = header("apns-request-id");
+
+    if !status.is_success() {
+        return Err(Error::from_response(Response {
+            apns_id: apns_request_id,
+            apns_unique_id: None,
+            error: serde_json::from_slice(body).ok(),
+            code: status.as_u16(),
+            delivery: None,
+            reconnected: false,
+        }));
+    }
+
+    let (channel_id, message_storage_policy) = match operation {
+        ChannelOperation::Create(message_storage_policy) => {
+            let Some(channel_id) = header("apns-channel-id") else {
+                return Err(Error::InvalidResponse(String::from(
+                    "The apns-channel-id of the created channel is missing.",
+                )));
+            };
+
+            (channel_id, Some(message_storage_policy))
+        }
+        ChannelOperation::Read(channel_id) => {
+            let body: ChannelBody = serde_json::from_slice(body)?;
+            (channel_id.as_str().to_owned(), Some(body.message_storage_policy))
+        }
+        ChannelOperation::Delete(channel_id) => (channel_id.as_str().to_owned(), None),
+    };
+
+    Ok(Channel {
+        channel_id,
+        message_storage_policy,
+        apns_request_id,
+    })
+}
+
+/// The time APNs confirmed the device token invalid, if it rejected the
+/// notification with [`ErrorReason::Unregistered`] or
+/// [`ErrorReason::BadDeviceToken`].
+fn rejected_token_timestamp(result: &Result<Response, Error>) -> Option<Option<u64>> {
+    let Err(ResponseError(Response {
+        error:
+            Some(ErrorBody {
+                reason: ErrorReason::Unregistered | ErrorReason::BadDeviceToken,
+                timestamp,
+            }),
+        ..
+    })) = result
+    else {
+        return None;
+    };
+
+    Some(*timestamp)
+}
+
+/// The error for a failed request, [`Error::CertificatePinMismatch`] if the
+/// connection failed because the server didn't present a pinned certificate.
+fn connection_error(e: hyper_util::client::legacy::Error) -> Error {
+    match find_pin_mismatch(&e) {
+        Some(mismatch) => Error::CertificatePinMismatch(mismatch.host.clone()),
+        None => e.into(),
+    }
+}
+
+/// Create a [`rustls::ConfigBuilder`] with the provider preset and platform
+/// verifier enabled. The verified certificate chains are recorded in
+/// `connections` if given.
+fn client_config_builder(
+    connections: Option<&Arc<ConnectionTracker>>,
+) -> Result<rustls::ConfigBuilder<rustls::ClientConfig, rustls::client::WantsClientCert>, Error> {
+    let provider = crypto_provider();
+    let verifier = Arc::new(rustls_platform_verifier::Verifier::new(provider.clone())?);
 
-    Ok(rustls::client::ClientConfig::builder_with_provider(provider)
+    let builder = rustls::client::ClientConfig::builder_with_provider(provider)
         .with_safe_default_protocol_versions()?
-        .try_with_platform_verifier()?)
+        .dangerous();
+
+    Ok(match connections {
+        Some(connections) => {
+            builder.with_custom_certificate_verifier(Arc::new(RecordingVerifier::new(verifier, connections.clone())))
+        }
+        None => builder.with_custom_certificate_verifier(verifier),
+    })
+}
+
+/// Resolves the host of a connection, or always to the
+/// [`ClientConfig::resolve_override`]
+#[derive(Debug, Clone)]
+enum Resolver {
+    System(GaiResolver),
+    Fixed(SocketAddr),
+}
+
+impl Service<Name> for Resolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            Resolver::System(resolver) => resolver.poll_ready(cx),
+            Resolver::Fixed(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        match self {
+            Resolver::System(resolver) => {
+                let resolving = resolver.call(name);
+                Box::pin(async move { Ok(resolving.await?.collect::<Vec<_>>().into_iter()) })
+            }
+            Resolver::Fixed(addr) => {
+                let addrs = vec![*addr].into_iter();
+                Box::pin(async move { Ok(addrs) })
+            }
+        }
+    }
+}
+
+/// Create a connector for the given TLS config, also connecting to `http`
+/// URIs without TLS if `plaintext_h2` is set
+fn tls_connector(
+    config: rustls::ClientConfig,
+    connect_timeout_secs: Option<u64>,
+    resolve_override: Option<SocketAddr>,
+    plaintext_h2: bool,
+) -> HyperConnector {
+    let resolver = match resolve_override {
+        Some(addr) => Resolver::Fixed(addr),
+        None => Resolver::System(GaiResolver::new()),
+    };
+
+    let mut http = HttpConnector::new_with_resolver(resolver);
+    http.enforce_http(false);
+    http.set_connect_timeout(connect_timeout_secs.map(Duration::from_secs));
+
+    let builder = HttpsConnectorBuilder::new().with_tls_config(config);
+    let builder = if plaintext_h2 {
+        builder.https_or_http()
+    } else {
+        builder.https_only()
+    };
+
+    builder.enable_http2().wrap_connector(http)
 }
 
 /// Create a connector with safe defaults
-fn default_connector() -> Result<HyperConnector, Error> {
-    let config = client_config_builder()?.with_no_client_auth();
+fn default_connector(
+    connections: &Arc<ConnectionTracker>,
+    connect_timeout_secs: Option<u64>,
+    resolve_override: Option<SocketAddr>,
+    plaintext_h2: bool,
+) -> Result<HyperConnector, Error> {
+    let config = client_config_builder(Some(connections))?.with_no_client_auth();
 
-    Ok(HttpsConnectorBuilder::new()
-        .with_tls_config(config)
-        .https_only()
-        .enable_http2()
-        .build())
+    Ok(tls_connector(
+        config,
+        connect_timeout_secs,
+        resolve_override,
+        plaintext_h2,
+    ))
 }
 
-fn client_cert_connector(cert_pem: &[u8], key_pem: &[u8]) -> Result<HyperConnector, Error> {
+fn client_cert_connector(
+    cert_pem: &[u8],
+    key_pem: &[u8],
+    connections: &Arc<ConnectionTracker>,
+    connect_timeout_secs: Option<u64>,
+    resolve_override: Option<SocketAddr>,
+    plaintext_h2: bool,
+) -> Result<HyperConnector, Error> {
+    Ok(tls_connector(
+        client_cert_config(cert_pem, key_pem, Some(connections))?,
+        connect_timeout_secs,
+        resolve_override,
+        plaintext_h2,
+    ))
+}
+
+/// Create a TLS config authenticating with the PEM-encoded certificate chain
+/// and PKCS#8 private key
+fn client_cert_config(
+    cert_pem: &[u8],
+    key_pem: &[u8],
+    connections: Option<&Arc<ConnectionTracker>>,
+) -> Result<rustls::ClientConfig, Error> {
     use rustls_pki_types::{CertificateDer, PrivatePkcs8KeyDer, pem::PemObject};
 
     let cert_error_fn = |e: rustls_pki_types::pem::Error| io::Error::new(io::ErrorKind::InvalidData, e);
@@ -385,23 +1839,124 @@ fn client_cert_connector(cert_pem: &[u8], key_pem: &[u8]) -> Result<HyperConnect
         .collect::<Result<Vec<_>, _>>()
         .map_err(cert_error_fn)?;
 
-    let config = client_config_builder()?.with_client_auth_cert(cert_chain, key.into())?;
+    Ok(client_config_builder(connections)?.with_client_auth_cert(cert_chain, key.into())?)
+}
+
+/// The end of the validity period of the first certificate in the PEM-encoded
+/// chain
+fn client_cert_not_after(cert_pem: &[u8]) -> Result<OffsetDateTime, Error> {
+    use rustls_pki_types::{CertificateDer, pem::PemObject};
+
+    let cert = CertificateDer::from_pem_slice(cert_pem).map_err(|_| Error::InvalidCertificate)?;
+    let (_, cert) = x509_parser::parse_x509_certificate(&cert).map_err(|_| Error::InvalidCertificate)?;
+
+    Ok(cert.validity().not_after.to_datetime())
+}
+
+/// Parse a PKCS#12 archive into a PEM-encoded certificate chain and private key
+#[cfg(feature = "ring")]
+fn pkcs12_to_pem(certificate_bytes: &[u8], password: &str) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    crate::pkcs12::parse_pkcs12(certificate_bytes, password)
+}
+
+/// Parse a PKCS#12 archive into a PEM-encoded certificate and private key
+#[cfg(all(not(feature = "ring"), feature = "openssl"))]
+fn pkcs12_to_pem(certificate_bytes: &[u8], password: &str) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let pkcs = openssl::pkcs12::Pkcs12::from_der(certificate_bytes)?.parse2(password)?;
+    let Some((cert, pkey)) = pkcs.cert.zip(pkcs.pkey) else {
+        return Err(Error::InvalidCertificate);
+    };
+    Ok((cert.to_pem()?, pkey.private_key_to_pem_pkcs8()?))
+}
+
+/// Load a provider certificate and its private key from either a PKCS#12
+/// archive (PFX/.p12) or a PEM bundle, returning the PEM-encoded certificate
+/// chain and PKCS#8 private key, e.g. for [`Client::certificate_parts`].
+///
+/// PEM data is recognized by its `-----BEGIN` line and needs at least one
+/// `CERTIFICATE` and an unencrypted `PRIVATE KEY`, the `password` is ignored.
+/// A PKCS#12 archive without a `password` is opened with an empty one.
+///
+/// ```no_run
+/// # use apns_h2::client::load_identity;
+/// # use apns_h2::{Client, ClientConfig};
+/// # fn main() -> Result<(), apns_h2::Error> {
+/// let identity = std::fs::read("/path/to/cert.p12_or_pem")?;
+/// let (cert_pem, key_pem) = load_identity(&identity, Some("Correct Horse Battery Stable"))?;
+/// let client = Client::certificate_parts(&cert_pem, &key_pem, ClientConfig::default())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_identity(data: &[u8], password: Option<&str>) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    if data.trim_ascii_start().starts_with(b"-----BEGIN") {
+        split_pem_bundle(data)
+    } else {
+        pkcs12_to_pem(data, password.unwrap_or_default())
+    }
+}
+
+/// Split a PEM bundle into the certificates and the first PKCS#8 private key
+fn split_pem_bundle(data: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    use rustls_pki_types::pem::{PemObject, SectionKind};
+
+    let mut cert_pem = Vec::new();
+    let mut key_pem = Vec::new();
+
+    for section in <(SectionKind, Vec<u8>)>::pem_slice_iter(data) {
+        match section.map_err(|_| Error::InvalidCertificate)? {
+            (SectionKind::Certificate, der) => cert_pem.extend(encode_pem("CERTIFICATE", &der)),
+            (SectionKind::PrivateKey, der) if key_pem.is_empty() => key_pem = encode_pem("PRIVATE KEY", &der),
+            _ => {}
+        }
+    }
+
+    if cert_pem.is_empty() || key_pem.is_empty() {
+        return Err(Error::InvalidCertificate);
+    }
+
+    Ok((cert_pem, key_pem))
+}
+
+/// A PEM block with the `label`, wrapping the base64 at 64 characters
+fn encode_pem(label: &str, der: &[u8]) -> Vec<u8> {
+    use base64::prelude::*;
+
+    let base64 = BASE64_STANDARD.encode(der);
+    let mut pem = format!("-----BEGIN {label}-----\n");
+
+    for line in base64.as_bytes().chunks(64) {
+        pem.push_str(&String::from_utf8_lossy(line));
+        pem.push('\n');
+    }
+
+    pem.push_str(&format!("-----END {label}-----\n"));
+    pem.into_bytes()
+}
+
+/// Build the TLS config used by [`Client::certificate`] from a PKCS#12
+/// provider certificate (PFX/.p12), including the platform verifier and the
+/// client certificate and key.
+///
+/// The config doesn't check [`ClientConfig::pinned_spki`], create the client
+/// with [`Client::certificate`] to pin the APNs certificates.
+pub fn build_client_tls_config(pfx: &[u8], password: &str) -> Result<rustls::ClientConfig, Error> {
+    let (cert_pem, key_pem) = pkcs12_to_pem(pfx, password)?;
 
-    Ok(HttpsConnectorBuilder::new()
-        .with_tls_config(config)
-        .https_only()
-        .enable_http2()
-        .build())
+    client_cert_config(&cert_pem, &key_pem, None)
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
-    use crate::PushType;
+    use crate::id::SequentialIdGenerator;
+    use crate::request::channel::MessageStoragePolicy;
     use crate::request::notification::DefaultNotificationBuilder;
     use crate::request::notification::NotificationBuilder;
     use crate::request::notification::{CollapseId, NotificationOptions, Priority};
+    use crate::request::payload::Payload;
+    use crate::request::target::ChannelId;
     use crate::signer::Signer;
+    use base64::prelude::*;
     use http::header::{AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE};
     use hyper::Method;
 
@@ -416,7 +1971,7 @@ jDwmlD1Gg0yJt1e38djFwsxsfr5q2hv0Rj9fTEqAPr8H7mGm0wKxZ7iQ
         let builder = DefaultNotificationBuilder::new();
         let payload = builder.build("a_test_id", Default::default());
         let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
+        let request = client.build_request(&payload).unwrap();
         let uri = format!("{}", request.uri());
 
         assert_eq!("https://api.push.apple.com/3/device/a_test_id", &uri);
@@ -433,272 +1988,2002 @@ jDwmlD1Gg0yJt1e38djFwsxsfr5q2hv0Rj9fTEqAPr8H7mGm0wKxZ7iQ
             })
             .build()
             .unwrap();
-        let request = client.build_request(payload).unwrap();
+        let request = client.build_request(&payload).unwrap();
         let uri = format!("{}", request.uri());
 
         assert_eq!("https://api.sandbox.push.apple.com/3/device/a_test_id", &uri);
     }
 
+    #[test]
+    fn test_client_endpoint() {
+        let client = Client::builder()
+            .config(ClientConfig::new(Endpoint::sandbox()))
+            .build()
+            .unwrap();
+
+        assert!(matches!(client.endpoint(), Endpoint::Sandbox));
+        assert_eq!("api.sandbox.push.apple.com", client.endpoint().host());
+        assert_eq!("production", Client::builder().build().unwrap().endpoint().name());
+    }
+
     #[test]
     fn test_request_method() {
         let builder = DefaultNotificationBuilder::new();
         let payload = builder.build("a_test_id", Default::default());
         let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
+        let request = client.build_request(&payload).unwrap();
 
         assert_eq!(&Method::POST, request.method());
     }
 
+    #[test]
+    fn test_probe_request() {
+        let client = Client::builder().build().unwrap();
+        let request = client.build_probe_request().unwrap();
+        let uri = format!("{}", request.uri());
+
+        assert_eq!(&Method::GET, request.method());
+        assert_eq!("https://api.push.apple.com/3/device/", &uri);
+        assert_eq!(None, request.headers().get(AUTHORIZATION));
+    }
+
     #[test]
     fn test_request_invalid() {
         let builder = DefaultNotificationBuilder::new();
         let payload = builder.build("\r\n", Default::default());
         let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload);
+        let request = client.build_request(&payload);
 
         assert!(matches!(request, Err(Error::BuildRequestError(_))));
     }
 
     #[test]
-    fn test_request_content_type() {
-        let builder = DefaultNotificationBuilder::new();
-        let payload = builder.build("a_test_id", Default::default());
-        let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
+    fn test_client_with_connect_timeout() {
+        let client = Client::builder().config(ClientConfig {
+            connect_timeout_secs: Some(5),
+            ..Default::default()
+        });
 
-        assert_eq!("application/json", request.headers().get(CONTENT_TYPE).unwrap());
+        assert!(client.build().is_ok());
     }
 
-    #[test]
-    fn test_request_content_length() {
-        let builder = DefaultNotificationBuilder::new();
-        let payload = builder.build("a_test_id", Default::default());
-        let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload.clone()).unwrap();
-        let payload_json = payload.to_json_string().unwrap();
-        let content_length = request.headers().get(CONTENT_LENGTH).unwrap().to_str().unwrap();
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        use std::sync::atomic::Ordering;
 
-        assert_eq!(&format!("{}", payload_json.len()), content_length);
+        let (addr, accepted, closed) = serve_h2c_counting(|_| async { (200, "") }).await;
+        let client = h2c_client(
+            addr,
+            ClientConfig {
+                idle_timeout: Some(Duration::from_millis(200)),
+                ..Default::default()
+            },
+        );
+        let send = || {
+            let payload = DefaultNotificationBuilder::new()
+                .badge(1)
+                .build("a_test_id", Default::default());
+
+            client.send(payload)
+        };
+
+        assert!(send().await.is_ok());
+        assert!(!send().await.unwrap().reconnected);
+        assert_eq!(1, accepted.load(Ordering::SeqCst));
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        assert_eq!(1, closed.load(Ordering::SeqCst));
+        assert!(send().await.unwrap().reconnected);
+        assert_eq!(2, accepted.load(Ordering::SeqCst));
     }
 
-    #[test]
-    fn test_request_authorization_with_no_signer() {
-        let builder = DefaultNotificationBuilder::new();
-        let payload = builder.build("a_test_id", Default::default());
-        let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
+    #[tokio::test]
+    async fn test_request_timeout_cancels_only_its_stream() {
+        let addr = serve_h2c_with(|request| async move {
+            if request.uri.path() == "/3/device/slow" {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
 
-        assert_eq!(None, request.headers().get(AUTHORIZATION));
+            (200, "")
+        })
+        .await;
+
+        let client = h2c_client(
+            addr,
+            ClientConfig {
+                request_timeout_secs: Some(1),
+                ..Default::default()
+            },
+        );
+        let send = |token: &'static str| {
+            let client = client.clone();
+
+            tokio::spawn(async move {
+                let payload = DefaultNotificationBuilder::new()
+                    .badge(1)
+                    .build(token, Default::default());
+
+                client.send(payload).await
+            })
+        };
+
+        // Opens the connection shared by the following sends
+        assert!(send("fast").await.unwrap().unwrap().reconnected);
+
+        let slow = send("slow");
+        let fast = (0..10).map(|_| send("fast")).collect::<Vec<_>>();
+
+        for fast in fast {
+            assert!(!fast.await.unwrap().unwrap().reconnected);
+        }
+
+        // The fast sends didn't wait for the slow one
+        assert!(!slow.is_finished());
+        assert!(matches!(slow.await.unwrap(), Err(Error::RequestTimeout(1))));
+        assert!(!send("fast").await.unwrap().unwrap().reconnected);
     }
 
-    #[test]
-    fn test_request_authorization_with_a_signer() {
-        let signer = Signer::new(
-            PRIVATE_KEY.as_bytes(),
-            "89AFRD1X22",
-            "ASDFQWERTY",
-            Duration::from_secs(100),
-        )
-        .unwrap();
+    #[tokio::test]
+    async fn test_resolve_override() {
+        let addr: SocketAddr = "17.188.0.1:443".parse().unwrap();
+        let mut resolver = Resolver::Fixed(addr);
+        let addrs = resolver.call("api.push.apple.com".parse().unwrap()).await.unwrap();
+
+        assert_eq!(vec![addr], addrs.collect::<Vec<_>>());
+
+        let client = Client::builder().config(ClientConfig {
+            resolve_override: Some(addr),
+            ..Default::default()
+        });
+
+        assert!(client.build().is_ok());
+    }
+
+    /// Starts a local cleartext HTTP/2 server answering every request with
+    /// the status and body `respond` returns for the request path.
+    async fn serve_h2c(respond: fn(&str) -> (u16, &'static str)) -> SocketAddr {
+        serve_h2c_with(move |request| {
+            let answer = respond(request.uri.path());
+            async move { answer }
+        })
+        .await
+    }
+
+    /// An answer of the local server, with an `apns-id` of
+    /// 00000000-0000-0000-0000-000000000001 unless `headers` set another.
+    struct MockResponse {
+        status: u16,
+        body: &'static str,
+        headers: &'static [(&'static str, &'static str)],
+    }
+
+    impl From<(u16, &'static str)> for MockResponse {
+        fn from((status, body): (u16, &'static str)) -> Self {
+            (status, body, &[][..]).into()
+        }
+    }
+
+    impl From<(u16, &'static str, &'static [(&'static str, &'static str)])> for MockResponse {
+        fn from((status, body, headers): (u16, &'static str, &'static [(&'static str, &'static str)])) -> Self {
+            Self { status, body, headers }
+        }
+    }
+
+    /// Starts a local cleartext HTTP/2 server answering every request with
+    /// the status and body, and optionally headers, `respond` resolves to for
+    /// the request head, e.g. to answer with a delay or depending on earlier
+    /// requests.
+    async fn serve_h2c_with<F, R, O>(respond: F) -> SocketAddr
+    where
+        F: Fn(http::request::Parts) -> R + Send + Sync + 'static,
+        R: Future<Output = O> + Send + 'static,
+        O: Into<MockResponse>,
+    {
+        serve_h2c_counting(respond).await.0
+    }
+
+    /// Like [`serve_h2c_with`], also returning the number of connections the
+    /// server accepted and the number of those closed.
+    async fn serve_h2c_counting<F, R, O>(
+        respond: F,
+    ) -> (
+        SocketAddr,
+        Arc<std::sync::atomic::AtomicUsize>,
+        Arc<std::sync::atomic::AtomicUsize>,
+    )
+    where
+        F: Fn(http::request::Parts) -> R + Send + Sync + 'static,
+        R: Future<Output = O> + Send + 'static,
+        O: Into<MockResponse>,
+    {
+        use hyper::server::conn::http2;
+        use hyper::service::service_fn;
+        use hyper_util::rt::TokioIo;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let respond = Arc::new(respond);
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let closed = Arc::new(AtomicUsize::new(0));
+        let counters = (accepted.clone(), closed.clone());
+
+        tokio::spawn(async move {
+            let (accepted, closed) = counters;
+
+            while let Ok((stream, _)) = listener.accept().await {
+                accepted.fetch_add(1, Ordering::SeqCst);
+
+                let respond = respond.clone();
+                let service = service_fn(move |request: hyper::Request<hyper::body::Incoming>| {
+                    let respond = respond.clone();
+
+                    async move {
+                        let (parts, body) = request.into_parts();
+
+                        // Responding before the body is read would reset the stream
+                        body.collect().await?;
+
+                        let answer = respond(parts).await.into();
+                        let mut response = hyper::Response::builder()
+                            .status(answer.status)
+                            .header("apns-id", "00000000-0000-0000-0000-000000000001")
+                            .body(Full::new(Bytes::from_static(answer.body.as_bytes())))
+                            .unwrap();
+
+                        for (name, value) in answer.headers {
+                            response.headers_mut().insert(*name, HeaderValue::from_static(value));
+                        }
+
+                        Ok::<_, hyper::Error>(response)
+                    }
+                });
+
+                let connection =
+                    http2::Builder::new(TokioExecutor::new()).serve_connection(TokioIo::new(stream), service);
+                let closed = closed.clone();
+
+                tokio::spawn(async move {
+                    let _ = connection.await;
+                    closed.fetch_add(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        (addr, accepted, closed)
+    }
+
+    /// A client sending to the local server at `addr` without TLS
+    fn h2c_client(addr: SocketAddr, config: ClientConfig) -> Client {
+        Client::builder()
+            .config(ClientConfig {
+                resolve_override: Some(addr),
+                plaintext_h2: true,
+                ..config
+            })
+            .build()
+            .unwrap()
+    }
+
+    /// Accepts one cleartext HTTP/2 connection and returns the settings the
+    /// client advertised with the increment of its connection window, read
+    /// until the first request arrives.
+    async fn read_h2_settings(listener: tokio::net::TcpListener) -> (HashMap<u16, u32>, Option<u32>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut preface = [0; 24];
+        stream.read_exact(&mut preface).await.unwrap();
+        assert_eq!(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n", &preface);
+
+        // An empty SETTINGS frame of the server
+        stream.write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await.unwrap();
+
+        let mut settings = HashMap::new();
+        let mut window_increment = None;
+
+        loop {
+            let mut header = [0; 9];
+            stream.read_exact(&mut header).await.unwrap();
+
+            let length = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+            let (frame_type, flags) = (header[3], header[4]);
+            let stream_id = u32::from_be_bytes([header[5], header[6], header[7], header[8]]);
+            let mut payload = vec![0; length];
+            stream.read_exact(&mut payload).await.unwrap();
+
+            match frame_type {
+                // HEADERS of the first request
+                0x1 => return (settings, window_increment),
+                // SETTINGS, unless it is an ACK
+                0x4 if flags & 0x1 == 0 => {
+                    for setting in payload.chunks_exact(6) {
+                        settings.insert(
+                            u16::from_be_bytes([setting[0], setting[1]]),
+                            u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]]),
+                        );
+                    }
+                }
+                // WINDOW_UPDATE of the connection
+                0x8 if stream_id == 0 => {
+                    window_increment = Some(u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_with_h2_settings() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = h2c_client(
+            listener.local_addr().unwrap(),
+            ClientConfig {
+                h2_settings: H2Settings {
+                    header_table_size: Some(0),
+                    max_frame_size: Some(1 << 20),
+                    max_header_list_size: Some(16 << 10),
+                    initial_stream_window_size: Some(1 << 20),
+                    initial_connection_window_size: Some(2 << 20),
+                },
+                ..Default::default()
+            },
+        );
+
+        let sending = tokio::spawn(async move {
+            let payload = DefaultNotificationBuilder::new()
+                .body("Hi")
+                .build("a_test_id", Default::default());
+
+            client.send(payload).await
+        });
+        let (settings, window_increment) = read_h2_settings(listener).await;
+        sending.abort();
+
+        // SETTINGS_HEADER_TABLE_SIZE
+        assert_eq!(Some(&0), settings.get(&0x1));
+        // SETTINGS_ENABLE_PUSH
+        assert_eq!(Some(&0), settings.get(&0x2));
+        // SETTINGS_INITIAL_WINDOW_SIZE
+        assert_eq!(Some(&(1 << 20)), settings.get(&0x4));
+        // SETTINGS_MAX_FRAME_SIZE
+        assert_eq!(Some(&(1 << 20)), settings.get(&0x5));
+        // SETTINGS_MAX_HEADER_LIST_SIZE
+        assert_eq!(Some(&(16 << 10)), settings.get(&0x6));
+        // The connection window starts at 65535 bytes and is grown to the
+        // configured size
+        assert_eq!(Some((2 << 20) - 65535), window_increment);
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_h2() {
+        let addr = serve_h2c(|_| (200, "")).await;
+        let client = h2c_client(addr, ClientConfig::default());
+
+        let payload = DefaultNotificationBuilder::new()
+            .body("Hi")
+            .build("a_test_id", Default::default());
+        let response = client.send(payload).await.unwrap();
+
+        assert_eq!(200, response.code);
+        assert_eq!(
+            Some(String::from("00000000-0000-0000-0000-000000000001")),
+            response.apns_id
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe() {
+        let addr = serve_h2c(|_| (405, r#"{"reason":"MethodNotAllowed"}"#)).await;
+
+        assert!(h2c_client(addr, ClientConfig::default()).probe().await.is_ok());
+
+        // Nothing listens on the port once the listener is dropped
+        let addr = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        assert!(matches!(
+            h2c_client(addr, ClientConfig::default()).probe().await,
+            Err(Error::ClientError(e)) if e.is_connect()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send_audited() {
+        let addr = serve_h2c(|path| match path {
+            "/3/device/a1b2c3d4e5f6" => (200, ""),
+            _ => (400, r#"{"reason":"BadDeviceToken"}"#),
+        })
+        .await;
+        let client = h2c_client(addr, ClientConfig::default());
+        let payload = |token| {
+            DefaultNotificationBuilder::new()
+                .badge(1)
+                .build(token, Default::default())
+        };
+
+        let (response, audit) = client.send_audited(payload("a1b2c3d4e5f6")).await.unwrap();
+
+        assert_eq!(200, response.code);
+        assert_eq!("a1b2****e5f6", audit.target);
+        assert_eq!(1, audit.attempts);
+        assert!(audit.sent_at.is_some());
+        assert!(audit.body_bytes > 0);
+        assert!(!audit.headers.iter().any(|(name, _)| name == "authorization"));
+        assert_eq!(Some("00000000-0000-0000-0000-000000000001"), audit.apns_id.as_deref());
+
+        let Err((Error::ResponseError(response), audit)) = client.send_audited(payload("f6e5d4c3b2a1")).await else {
+            panic!("Expected the rejection with its audit record");
+        };
+
+        assert_eq!(400, response.code);
+        assert_eq!("f6e5****b2a1", audit.target);
+        assert_eq!(1, audit.attempts);
+        assert!(audit.sent_at.is_some());
+        assert_eq!(Some("00000000-0000-0000-0000-000000000001"), audit.apns_id.as_deref());
+    }
+
+    #[tokio::test]
+    async fn test_send_verbose() {
+        const BODY: &str = r#"{"reason":"BadDeviceToken"}"#;
+
+        let addr = serve_h2c_with(|_| async {
+            let headers = &[
+                ("apns-unique-id", "a8c2b6e4-1f3d-4c5b-9e7a-0d1f2e3c4b5a"),
+                ("x-trace", "mock-1"),
+            ][..];
+
+            (400, BODY, headers)
+        })
+        .await;
+        let client = h2c_client(addr, ClientConfig::default());
+        let payload = DefaultNotificationBuilder::new()
+            .badge(1)
+            .build("a_test_id", Default::default());
+
+        let response = client.send_verbose(payload).await.unwrap();
+
+        assert_eq!(http::StatusCode::BAD_REQUEST, response.status);
+        assert_eq!("00000000-0000-0000-0000-000000000001", response.headers["apns-id"]);
+        assert_eq!(
+            "a8c2b6e4-1f3d-4c5b-9e7a-0d1f2e3c4b5a",
+            response.headers["apns-unique-id"]
+        );
+        assert_eq!("mock-1", response.headers["x-trace"]);
+        assert_eq!(BODY, response.body);
+    }
+
+    #[tokio::test]
+    async fn test_payload_rejected_by_apns() {
+        let addr = serve_h2c(|_| (413, r#"{"reason":"PayloadTooLarge"}"#)).await;
+        let client = h2c_client(addr, ClientConfig::default());
+        let payload = DefaultNotificationBuilder::new()
+            .body("Hi")
+            .build("a_test_id", Default::default());
+
+        let Err(Error::PayloadRejected(response)) = client.send(payload).await else {
+            panic!("Expected the response of APNs");
+        };
+
+        assert_eq!(413, response.code);
+        assert_eq!(
+            Some(ErrorReason::PayloadTooLarge),
+            response.error.map(|error| error.reason)
+        );
+        assert_eq!(
+            Some("00000000-0000-0000-0000-000000000001"),
+            response.apns_id.as_deref()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apns_id_mismatch() {
+        let sent = "a6c6a1b4-3b7c-4f5e-9a0d-1c2b3d4e5f60";
+
+        assert!(!is_apns_id_mismatch(Some(sent), Some(sent)));
+        assert!(!is_apns_id_mismatch(Some(sent), Some(&sent.to_uppercase())));
+        assert!(!is_apns_id_mismatch(None, Some(sent)));
+        assert!(!is_apns_id_mismatch(Some(sent), None));
+        assert!(is_apns_id_mismatch(
+            Some(sent),
+            Some("00000000-0000-0000-0000-000000000001")
+        ));
+
+        #[cfg(feature = "tracing")]
+        let (logs, _subscriber) = capture_logs();
+
+        // The server always answers with 00000000-0000-0000-0000-000000000001
+        let addr = serve_h2c(|_| (200, "")).await;
+        let client = h2c_client(addr, ClientConfig::default());
+        let payload = |apns_id| {
+            DefaultNotificationBuilder::new().badge(1).build(
+                "a1b2c3d4",
+                NotificationOptions {
+                    apns_id: Some(apns_id),
+                    ..Default::default()
+                },
+            )
+        };
+
+        let response = client.send(payload(sent)).await.unwrap();
+
+        assert_eq!(
+            Some("00000000-0000-0000-0000-000000000001"),
+            response.apns_id.as_deref()
+        );
+
+        client
+            .send(payload("00000000-0000-0000-0000-000000000001"))
+            .await
+            .unwrap();
+
+        #[cfg(feature = "tracing")]
+        {
+            let logs = String::from_utf8(logs.lock().clone()).unwrap();
+            let warnings = logs
+                .lines()
+                .filter(|line| line.contains("WARN") && line.contains("APNs answered with the apns-id"))
+                .collect::<Vec<_>>();
+
+            assert_eq!(1, warnings.len(), "{logs}");
+            assert!(warnings[0].contains(sent));
+        }
+    }
+
+    /// Collects the log output of the current thread until the returned
+    /// guard is dropped.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn capture_logs() -> (Arc<parking_lot::Mutex<Vec<u8>>>, tracing::subscriber::DefaultGuard) {
+        let logs = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let writer_logs = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer(move || LogWriter(writer_logs.clone()))
+            .finish();
+
+        (logs, tracing::subscriber::set_default(subscriber))
+    }
+
+    /// Collects the log output of a test subscriber
+    #[cfg(feature = "tracing")]
+    struct LogWriter(Arc<parking_lot::Mutex<Vec<u8>>>);
+
+    #[cfg(feature = "tracing")]
+    impl io::Write for LogWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_live_activity() {
+        let addr = serve_h2c(|path| match path {
+            "/3/device/a1b2c3d4" | "/4/broadcasts/apps/com.example.app" => (200, ""),
+            _ => (400, r#"{"reason":"BadPath"}"#),
+        })
+        .await;
+
+        let client = h2c_client(
+            addr,
+            ClientConfig {
+                clock: Arc::new(crate::clock::ManualClock::new(1672531200)),
+                default_options: NotificationOptions {
+                    apns_topic: Some("com.example.app"),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        let content_state = json!({"score": "2:1"});
+        let channel_id = ChannelId::new("dHN0LXNyY2gtY2hubA==").unwrap();
+
+        let payload = client.live_activity_update(channel_id.clone(), &content_state, Default::default());
+
+        assert_eq!(
+            json!({
+                "aps": {
+                    "mutable-content": 0,
+                    "timestamp": 1672531200,
+                    "event": "update",
+                    "content-state": {"score": "2:1"},
+                }
+            }),
+            serde_json::to_value(&payload).unwrap()
+        );
+
+        let request = client.build_request(&payload).unwrap();
+
+        assert_eq!("liveactivity", request.headers().get("apns-push-type").unwrap());
+        assert_eq!(
+            "dHN0LXNyY2gtY2hubA==",
+            request.headers().get("apns-channel-id").unwrap()
+        );
+
+        assert!(
+            client
+                .update_live_activity("a1b2c3d4", &content_state, Default::default())
+                .await
+                .is_ok()
+        );
+        assert!(
+            client
+                .update_live_activity(channel_id, &content_state, Default::default())
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_campaign() {
+        let addr = serve_h2c(|path| match path {
+            "/3/device/dead" => (410, r#"{"reason":"Unregistered","timestamp":1508249865488}"#),
+            "/3/device/broken" => (500, r#"{"reason":"InternalServerError"}"#),
+            _ => (200, ""),
+        })
+        .await;
+
+        let purged = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let client = h2c_client(
+            addr,
+            ClientConfig {
+                on_invalid_token: Some(Arc::new({
+                    let purged = purged.clone();
+                    move |token: &str, _| purged.lock().push(token.to_owned())
+                })),
+                ..Default::default()
+            },
+        );
+
+        let payload = DefaultNotificationBuilder::new()
+            .body("50% off today")
+            .build("", Default::default());
+        let policy = Arc::new(crate::retry::ExponentialBackoff {
+            max_retries: 1,
+            base_delay: Duration::ZERO,
+        });
+
+        let report = client
+            .run_campaign(
+                payload,
+                ["alive", "dead", "broken", "also_alive"],
+                Default::default(),
+                policy,
+            )
+            .await;
+
+        assert_eq!(
+            CampaignReport {
+                delivered: 2,
+                failed: 1,
+                purged: 1,
+            },
+            report
+        );
+        assert_eq!(vec![String::from("dead")], *purged.lock());
+    }
+
+    #[test]
+    fn test_client_with_adaptive_window() {
+        let client = Client::builder().config(ClientConfig {
+            adaptive_window: true,
+            ..Default::default()
+        });
+
+        assert!(client.build().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_retry_policy() {
+        #[derive(Debug, Default)]
+        struct CountingPolicy(parking_lot::Mutex<Vec<u32>>);
+
+        impl RetryPolicy for CountingPolicy {
+            fn next_delay<'a>(&'a self, attempt: u32, err: &'a Error) -> crate::retry::RetryDelay<'a> {
+                self.0.lock().push(attempt);
+
+                let retry = matches!(err, Error::PayloadTooLarge { .. }) && attempt < 3;
+                Box::pin(async move { retry.then_some(Duration::ZERO) })
+            }
+        }
+
+        let policy = Arc::new(CountingPolicy::default());
+        let client = Client::builder()
+            .config(ClientConfig {
+                retry_policy: Some(policy.clone()),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let payload = DefaultNotificationBuilder::new()
+            .body("a".repeat(4096))
+            .build("a_test_id", Default::default());
+
+        let result = client.send(payload).await;
+
+        assert!(matches!(result, Err(Error::PayloadTooLarge { .. })));
+        assert_eq!(vec![1, 2, 3], *policy.0.lock());
+    }
+
+    #[test]
+    fn test_report_invalid_token() {
+        let purged = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let client = Client::builder()
+            .config(ClientConfig {
+                on_invalid_token: Some(Arc::new({
+                    let purged = purged.clone();
+                    move |token: &str, timestamp: Option<u64>| purged.lock().push((token.to_owned(), timestamp))
+                })),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let payload = DefaultNotificationBuilder::new().build("a_dead_token", Default::default());
+        let rejected = |reason, timestamp| {
+            Err(ResponseError(Response {
+                error: Some(ErrorBody { reason, timestamp }),
+                apns_id: None,
+                apns_unique_id: None,
+                code: 410,
+                delivery: None,
+                reconnected: false,
+            }))
+        };
+
+        client.report_invalid_token(&payload, &rejected(ErrorReason::Unregistered, Some(1508249865488)));
+        client.report_invalid_token(&payload, &rejected(ErrorReason::BadDeviceToken, None));
+        client.report_invalid_token(&payload, &rejected(ErrorReason::TooManyRequests, None));
+        client.report_invalid_token(&payload, &Err(Error::RequestTimeout(20)));
+
+        assert_eq!(
+            vec![
+                (String::from("a_dead_token"), Some(1508249865488)),
+                (String::from("a_dead_token"), None),
+            ],
+            *purged.lock()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_renewing_expired_token() {
+        let authorizations = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let addr = serve_h2c_with({
+            let authorizations = authorizations.clone();
+
+            move |request| {
+                let mut authorizations = authorizations.lock();
+                authorizations.push(request.headers[AUTHORIZATION].to_str().unwrap().to_owned());
+
+                let answer = match (request.uri.path(), authorizations.len()) {
+                    ("/3/device/a_test_id", 1) | ("/3/device/always_expired", _) => {
+                        (403, r#"{"reason":"ExpiredProviderToken"}"#)
+                    }
+                    _ => (200, ""),
+                };
+
+                async move { answer }
+            }
+        })
+        .await;
+
+        let clock = Arc::new(crate::clock::ManualClock::new(1672531200));
+        let client = Client::with_token_auth(
+            TokenAuth {
+                key_id: String::from("89AFRD1X22"),
+                team_id: String::from("ASDFQWERTY"),
+                key: PRIVATE_KEY.as_bytes().to_vec(),
+            },
+            ClientConfig {
+                resolve_override: Some(addr),
+                plaintext_h2: true,
+                clock: clock.clone(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let issued_at = |authorization: &str| {
+            let claims = authorization.split('.').nth(1).unwrap();
+            let claims: serde_json::Value = serde_json::from_slice(&BASE64_STANDARD.decode(claims).unwrap()).unwrap();
+
+            claims["iat"].as_i64().unwrap()
+        };
+        let payload = |token| {
+            DefaultNotificationBuilder::new()
+                .badge(1)
+                .build(token, Default::default())
+        };
+
+        // Old enough to be renewed, but not yet expired by the client
+        clock.advance(Duration::from_secs(21 * 60));
+
+        assert!(client.send(payload("a_test_id")).await.is_ok());
+
+        {
+            let authorizations = authorizations.lock();
+
+            assert_eq!(2, authorizations.len());
+            assert_ne!(authorizations[0], authorizations[1]);
+            assert_eq!(1672531200, issued_at(&authorizations[0]));
+            assert_eq!(1672531200 + 21 * 60, issued_at(&authorizations[1]));
+        }
+
+        clock.advance(Duration::from_secs(21 * 60));
+
+        // Sent again only once with a renewed token
+        assert!(matches!(
+            client.send(payload("always_expired")).await,
+            Err(Error::Forbidden {
+                reason: Some(ErrorReason::ExpiredProviderToken),
+                ..
+            })
+        ));
+        assert_eq!(4, authorizations.lock().len());
+
+        // The token was just renewed and APNs rejects renewing it this early
+        assert!(client.send(payload("always_expired")).await.is_err());
+        assert_eq!(5, authorizations.lock().len());
+    }
+
+    #[test]
+    fn test_is_expired_provider_token() {
+        let response = |reason| Response {
+            error: Some(ErrorBody {
+                reason,
+                timestamp: None,
+            }),
+            apns_id: None,
+            apns_unique_id: None,
+            code: 403,
+            delivery: None,
+            reconnected: false,
+        };
+
+        assert!(is_expired_provider_token(&Err(Error::from_response(response(
+            ErrorReason::ExpiredProviderToken
+        )))));
+        assert!(!is_expired_provider_token(&Err(Error::from_response(response(
+            ErrorReason::InvalidProviderToken
+        )))));
+        assert!(!is_expired_provider_token(&Err(Error::RequestTimeout(20))));
+    }
+
+    #[tokio::test]
+    async fn test_send_owned_returns_payload_on_error() {
+        let builder = DefaultNotificationBuilder::new().title("a title");
+        let payload = builder.build("\r\n", Default::default());
+        let client = Client::builder().build().unwrap();
+
+        let (returned, error) = client.send_owned(payload.clone()).await.unwrap_err();
+
+        assert!(matches!(error, Error::BuildRequestError(_)));
+        assert_eq!(payload.target, returned.target);
+        assert_eq!(payload.to_json_string().unwrap(), returned.to_json_string().unwrap());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_send_all_with_slow_consumer() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let client = Client::builder().build().unwrap();
+        let taken = Arc::new(AtomicUsize::new(0));
+
+        let payloads = {
+            let taken = taken.clone();
+
+            (0..10)
+                .map(|_| DefaultNotificationBuilder::new().build("\r\n", Default::default()))
+                .inspect(move |_| {
+                    taken.fetch_add(1, Ordering::SeqCst);
+                })
+        };
+
+        let mut results = client.send_all(payloads, 2);
+
+        // With the time paused, the sleep only ends once the sending task is
+        // blocked on the full channel
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // Two results in the channel and one waiting to be sent
+        assert_eq!(3, taken.load(Ordering::SeqCst));
+
+        let mut count = 0;
+        while let Some(result) = results.recv().await {
+            assert!(matches!(result, Err(Error::BuildRequestError(_))));
+            count += 1;
+        }
+
+        assert_eq!(10, count);
+        assert_eq!(10, taken.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_send_all_with_zero_buffer() {
+        let client = Client::builder().build().unwrap();
+        let payloads = (0..3).map(|_| DefaultNotificationBuilder::new().build("\r\n", Default::default()));
+
+        let mut results = client.send_all(payloads, 0);
+
+        let mut count = 0;
+        while let Some(result) = results.recv().await {
+            assert!(matches!(result, Err(Error::BuildRequestError(_))));
+            count += 1;
+        }
+
+        assert_eq!(3, count);
+    }
+
+    #[tokio::test]
+    async fn test_send_deduped_within_window() {
+        let addr = serve_h2c(|path| match path {
+            "/3/device/broken" => (500, r#"{"reason":"InternalServerError"}"#),
+            _ => (200, ""),
+        })
+        .await;
+
+        let clock = Arc::new(crate::clock::ManualClock::new(1672531200));
+        let client = h2c_client(
+            addr,
+            ClientConfig {
+                dedup_window: Some(Duration::from_secs(60)),
+                clock: clock.clone(),
+                ..Default::default()
+            },
+        );
+        let payload = |token| {
+            DefaultNotificationBuilder::new()
+                .title("a title")
+                .build(token, Default::default())
+        };
+
+        // The second send finds the first one in flight
+        let (first, second) = tokio::join!(client.send(payload("a_test_id")), client.send(payload("a_test_id")));
+
+        assert!(first.is_ok());
+        assert!(matches!(second, Err(Error::Duplicate)));
+
+        clock.advance(Duration::from_secs(59));
+
+        assert!(matches!(client.send(payload("a_test_id")).await, Err(Error::Duplicate)));
+        assert!(client.send(payload("another_test_id")).await.is_ok());
+
+        clock.advance(Duration::from_secs(1));
+
+        assert!(client.send(payload("a_test_id")).await.is_ok());
+
+        // A failed send doesn't count as sent
+        assert!(matches!(
+            client.send(payload("broken")).await,
+            Err(Error::ResponseError(_))
+        ));
+        assert!(matches!(
+            client.send(payload("broken")).await,
+            Err(Error::ResponseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_request_content_type() {
+        let builder = DefaultNotificationBuilder::new();
+        let payload = builder.build("a_test_id", Default::default());
+        let client = Client::builder().build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+
+        assert_eq!("application/json", request.headers().get(CONTENT_TYPE).unwrap());
+    }
+
+    #[test]
+    fn test_request_content_length() {
+        let builder = DefaultNotificationBuilder::new();
+        let payload = builder.build("a_test_id", Default::default());
+        let client = Client::builder().build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+        let payload_json = payload.to_json_string().unwrap();
+        let content_length = request.headers().get(CONTENT_LENGTH).unwrap().to_str().unwrap();
+
+        assert_eq!(&format!("{}", payload_json.len()), content_length);
+    }
+
+    #[test]
+    fn test_request_authorization_with_no_signer() {
+        let builder = DefaultNotificationBuilder::new();
+        let payload = builder.build("a_test_id", Default::default());
+        let client = Client::builder().build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+
+        assert_eq!(None, request.headers().get(AUTHORIZATION));
+    }
+
+    #[test]
+    fn test_request_authorization_with_a_signer() {
+        let signer = Signer::new(
+            PRIVATE_KEY.as_bytes(),
+            "89AFRD1X22",
+            "ASDFQWERTY",
+            Duration::from_secs(100),
+            Arc::new(SystemClock),
+        )
+        .unwrap();
+
+        let builder = DefaultNotificationBuilder::new();
+        let payload = builder.build("a_test_id", Default::default());
+        let client = Client::builder().signer(signer).build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+
+        assert_ne!(None, request.headers().get(AUTHORIZATION));
+    }
+
+    #[test]
+    fn test_with_token_auth() {
+        let auth = TokenAuth {
+            key_id: String::from("89AFRD1X22"),
+            team_id: String::from("ASDFQWERTY"),
+            key: PRIVATE_KEY.as_bytes().to_vec(),
+        };
+
+        assert!(!format!("{:?}", auth).contains("PRIVATE KEY"));
+
+        let client = Client::with_token_auth(auth, ClientConfig::default()).unwrap();
+        let payload = DefaultNotificationBuilder::new().build("a_test_id", Default::default());
+        let request = client.build_request(&payload).unwrap();
+
+        assert!(request.headers().get(AUTHORIZATION).is_some());
+    }
+
+    #[test]
+    fn test_jwt_header() {
+        let auth = TokenAuth {
+            key_id: String::from("89AFRD1X22"),
+            team_id: String::from("ASDFQWERTY"),
+            key: PRIVATE_KEY.as_bytes().to_vec(),
+        };
+        let signed_header = |client: &Client| {
+            let payload = DefaultNotificationBuilder::new().build("a_test_id", Default::default());
+            let request = client.build_request(&payload).unwrap();
+            let token = request.headers()[AUTHORIZATION].to_str().unwrap().to_owned();
+            let header = token
+                .trim_start_matches("Bearer ")
+                .split('.')
+                .next()
+                .unwrap()
+                .to_owned();
+
+            serde_json::from_slice::<serde_json::Value>(&BASE64_STANDARD.decode(header).unwrap()).unwrap()
+        };
+
+        let client = Client::with_token_auth(auth.clone(), ClientConfig::default()).unwrap();
+        let header = client.jwt_header(None).unwrap().unwrap();
+
+        assert_eq!(
+            json!({"alg": "ES256", "kid": "89AFRD1X22"}),
+            serde_json::Value::Object(header)
+        );
+        assert_eq!(json!({"alg": "ES256", "kid": "89AFRD1X22"}), signed_header(&client));
+
+        let jwt_header = json!({"kid": "OVERRIDE12", "typ": "JWT"});
+        let client = Client::with_token_auth(
+            auth.clone(),
+            ClientConfig {
+                jwt_header: jwt_header.as_object().unwrap().clone(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let expected = json!({"alg": "ES256", "kid": "OVERRIDE12", "typ": "JWT"});
+
+        assert_eq!(
+            expected,
+            serde_json::Value::Object(client.jwt_header(None).unwrap().unwrap())
+        );
+        assert_eq!(expected, signed_header(&client));
+
+        let result = Client::with_token_auth(
+            auth,
+            ClientConfig {
+                jwt_header: json!({"alg": "HS256"}).as_object().unwrap().clone(),
+                ..Default::default()
+            },
+        );
+
+        assert!(matches!(result, Err(Error::InvalidOptions(_))));
+        assert_eq!(None, Client::builder().build().unwrap().jwt_header(None).unwrap());
+    }
+
+    #[test]
+    fn test_request_with_a_signing_key_id() {
+        let auth = |key_id: &str| TokenAuth {
+            key_id: String::from(key_id),
+            team_id: String::from("ASDFQWERTY"),
+            key: PRIVATE_KEY.as_bytes().to_vec(),
+        };
+        let client =
+            Client::with_token_auths(vec![auth("OLDKEY1234"), auth("NEWKEY1234")], ClientConfig::default()).unwrap();
+
+        let key_id_of = |signing_key_id| {
+            let payload = DefaultNotificationBuilder::new().build(
+                "a_test_id",
+                NotificationOptions {
+                    signing_key_id,
+                    ..Default::default()
+                },
+            );
+            let request = client.build_request(&payload)?;
+            let token = request.headers()[AUTHORIZATION].to_str().unwrap().to_owned();
+            let header = token
+                .trim_start_matches("Bearer ")
+                .split('.')
+                .next()
+                .unwrap()
+                .to_owned();
+            let header: serde_json::Value = serde_json::from_slice(&BASE64_STANDARD.decode(header).unwrap())?;
+
+            Ok::<_, Error>(header["kid"].as_str().unwrap().to_owned())
+        };
+
+        assert_eq!("OLDKEY1234", key_id_of(None).unwrap());
+        assert_eq!("OLDKEY1234", key_id_of(Some("OLDKEY1234")).unwrap());
+        assert_eq!("NEWKEY1234", key_id_of(Some("NEWKEY1234")).unwrap());
+        assert!(matches!(key_id_of(Some("UNKNOWN123")), Err(Error::InvalidOptions(_))));
+
+        assert!(matches!(
+            Client::with_token_auths(Vec::new(), ClientConfig::default()),
+            Err(Error::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn test_request_with_background_type() {
+        let builder = DefaultNotificationBuilder::new().content_available();
+        let options = NotificationOptions {
+            apns_push_type: Some(PushType::Background),
+            ..Default::default()
+        };
+        let payload = builder.build("a_test_id", options);
+        let client = Client::builder().build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+        let apns_push_type = request.headers().get("apns-push-type").unwrap();
+
+        assert_eq!("background", apns_push_type);
+    }
+
+    #[test]
+    fn test_request_with_inferred_push_type() {
+        let client = Client::builder().build().unwrap();
+        let push_type = |builder: DefaultNotificationBuilder<'_>| {
+            let payload = builder.build("a_test_id", Default::default());
+            let request = client.build_request(&payload).unwrap();
+
+            request.headers().get("apns-push-type").unwrap().clone()
+        };
+
+        assert_eq!("alert", push_type(DefaultNotificationBuilder::new().title("a title")));
+        assert_eq!("alert", push_type(DefaultNotificationBuilder::new().badge(1)));
+        assert_eq!(
+            "background",
+            push_type(DefaultNotificationBuilder::new().content_available())
+        );
+        assert_eq!(
+            "liveactivity",
+            push_type(
+                DefaultNotificationBuilder::new()
+                    .update_activity()
+                    .content_state(&json!({"score": 1}))
+            )
+        );
+    }
+
+    #[test]
+    fn test_request_with_push_type_of_topic() {
+        let push_type = |client: &Client, apns_topic| {
+            let payload = DefaultNotificationBuilder::new().badge(1).build(
+                "a_test_id",
+                NotificationOptions {
+                    apns_topic,
+                    ..Default::default()
+                },
+            );
+            let request = client.build_request(&payload).unwrap();
+
+            request.headers().get("apns-push-type").unwrap().clone()
+        };
+        let client = Client::builder().build().unwrap();
+
+        assert_eq!("alert", push_type(&client, Some("com.example.app")));
+        assert_eq!("voip", push_type(&client, Some("com.example.app.voip")));
+        assert_eq!("complication", push_type(&client, Some("com.example.app.complication")));
+        assert_eq!(
+            "fileprovider",
+            push_type(&client, Some("com.example.app.pushkit.fileprovider"))
+        );
+
+        let client = Client::builder()
+            .config(ClientConfig {
+                default_options: NotificationOptions {
+                    apns_topic: Some("com.example.app.voip"),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        assert_eq!("voip", push_type(&client, None));
+        assert_eq!("alert", push_type(&client, Some("com.example.app")));
+    }
+
+    #[test]
+    fn test_request_with_unknown_push_type() {
+        let client = Client::builder().build().unwrap();
+        let payload = DefaultNotificationBuilder::new()
+            .badge(1)
+            .build("a_test_id", Default::default());
+        let mut headers = RequestHeaders::default();
+        headers.insert("apns-push-type", "alerts");
+
+        assert!(matches!(
+            client.build_request_with_headers(&payload, Some(headers)),
+            Err(Error::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn test_request_revalidated_for_push_type() {
+        let client = Client::builder().build().unwrap();
+        let payload = |apns_push_type| {
+            DefaultNotificationBuilder::new().title("a title").build(
+                "a_test_id",
+                NotificationOptions {
+                    apns_push_type,
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert!(client.build_request(&payload(Some(PushType::Alert))).is_ok());
+        assert!(matches!(
+            client.build_request(&payload(Some(PushType::Background))),
+            Err(Error::ConflictingFields(_))
+        ));
+
+        let mut headers = RequestHeaders::default();
+        headers.insert("apns-push-type", "background");
+
+        assert!(matches!(
+            client.build_request_with_headers(&payload(None), Some(headers)),
+            Err(Error::ConflictingFields(_))
+        ));
+
+        // Not checked when disabled
+        let client = Client::builder()
+            .config(ClientConfig {
+                revalidate_push_type: false,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        assert!(client.build_request(&payload(Some(PushType::Background))).is_ok());
+    }
+
+    #[test]
+    fn test_request_with_default_priority() {
+        let builder = DefaultNotificationBuilder::new();
+        let payload = builder.build("a_test_id", Default::default());
+        let client = Client::builder().build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+        let apns_priority = request.headers().get("apns-priority");
+
+        assert_eq!(None, apns_priority);
+    }
+
+    #[test]
+    fn test_request_with_normal_priority() {
+        let builder = DefaultNotificationBuilder::new();
+
+        let payload = builder.build(
+            "a_test_id",
+            NotificationOptions {
+                apns_priority: Some(Priority::Normal),
+                ..Default::default()
+            },
+        );
+
+        let client = Client::builder().build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+        let apns_priority = request.headers().get("apns-priority").unwrap();
+
+        assert_eq!("5", apns_priority);
+    }
+
+    #[test]
+    fn test_request_with_priority_from_interruption_level() {
+        let client = Client::builder().build().unwrap();
+
+        let payload = DefaultNotificationBuilder::new()
+            .time_sensitive_interruption_level()
+            .build("a_test_id", Default::default());
+        let request = client.build_request(&payload).unwrap();
+
+        assert_eq!("10", request.headers().get("apns-priority").unwrap());
+
+        let payload = DefaultNotificationBuilder::new()
+            .time_sensitive_interruption_level()
+            .build(
+                "a_test_id",
+                NotificationOptions {
+                    apns_priority: Some(Priority::Normal),
+                    ..Default::default()
+                },
+            );
+        let request = client.build_request(&payload).unwrap();
+
+        assert_eq!("5", request.headers().get("apns-priority").unwrap());
+
+        let payload = DefaultNotificationBuilder::new()
+            .passive_interruption_level()
+            .build("a_test_id", Default::default());
+        let request = client.build_request(&payload).unwrap();
+
+        assert_eq!(None, request.headers().get("apns-priority"));
+    }
+
+    #[test]
+    fn test_request_with_high_priority() {
+        let builder = DefaultNotificationBuilder::new();
+
+        let payload = builder.build(
+            "a_test_id",
+            NotificationOptions {
+                apns_priority: Some(Priority::High),
+                ..Default::default()
+            },
+        );
+
+        let client = Client::builder().build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+        let apns_priority = request.headers().get("apns-priority").unwrap();
+
+        assert_eq!("10", apns_priority);
+    }
+
+    #[test]
+    fn test_request_with_default_apns_id() {
+        let builder = DefaultNotificationBuilder::new();
+
+        let payload = builder.build("a_test_id", Default::default());
+
+        let client = Client::builder().build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+        let apns_id = request.headers().get("apns-id");
+
+        assert_eq!(None, apns_id);
+    }
+
+    #[test]
+    fn test_request_with_a_generated_apns_id() {
+        let client = Client::builder()
+            .config(ClientConfig {
+                apns_id_generator: Some(Arc::new(SequentialIdGenerator::default())),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let payload = DefaultNotificationBuilder::new().build("a_test_id", Default::default());
+        let request = client.build_request(&payload).unwrap();
+
+        assert_eq!(
+            "00000000-0000-0000-0000-000000000001",
+            request.headers().get("apns-id").unwrap()
+        );
+
+        let payload = DefaultNotificationBuilder::new().build(
+            "a_test_id",
+            NotificationOptions {
+                apns_id: Some("a-test-apns-id"),
+                ..Default::default()
+            },
+        );
+        let request = client.build_request(&payload).unwrap();
+
+        assert_eq!("a-test-apns-id", request.headers().get("apns-id").unwrap());
+
+        let payload = DefaultNotificationBuilder::new().build("a_test_id", Default::default());
+        let request = client.build_request(&payload).unwrap();
+
+        assert_eq!(
+            "00000000-0000-0000-0000-000000000002",
+            request.headers().get("apns-id").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_request_with_an_apns_id() {
+        let builder = DefaultNotificationBuilder::new();
+
+        let payload = builder.build(
+            "a_test_id",
+            NotificationOptions {
+                apns_id: Some("a-test-apns-id"),
+                ..Default::default()
+            },
+        );
+
+        let client = Client::builder().build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+        let apns_id = request.headers().get("apns-id").unwrap();
+
+        assert_eq!("a-test-apns-id", apns_id);
+    }
+
+    #[test]
+    fn test_request_with_default_apns_expiration() {
+        let builder = DefaultNotificationBuilder::new();
+
+        let payload = builder.build("a_test_id", Default::default());
+
+        let client = Client::builder().build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+        let apns_expiration = request.headers().get("apns-expiration");
+
+        assert_eq!(None, apns_expiration);
+    }
+
+    #[test]
+    fn test_request_with_an_apns_expiration() {
+        let builder = DefaultNotificationBuilder::new();
+
+        let payload = builder.build(
+            "a_test_id",
+            NotificationOptions {
+                apns_expiration: Some(420),
+                ..Default::default()
+            },
+        );
+
+        let client = Client::builder().build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+        let apns_expiration = request.headers().get("apns-expiration").unwrap();
+
+        assert_eq!("420", apns_expiration);
+    }
+
+    #[test]
+    fn test_request_with_a_zero_apns_expiration() {
+        let payload = DefaultNotificationBuilder::new().build(
+            "a_test_id",
+            NotificationOptions {
+                apns_expiration: Some(0),
+                ..Default::default()
+            },
+        );
+
+        let client = Client::builder().build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+
+        // 0 asks APNs not to store the notification, so it is sent as well
+        assert_eq!("0", request.headers().get("apns-expiration").unwrap());
+    }
+
+    #[test]
+    fn test_request_with_default_apns_collapse_id() {
+        let builder = DefaultNotificationBuilder::new();
+
+        let payload = builder.build("a_test_id", Default::default());
+
+        let client = Client::builder().build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+        let apns_collapse_id = request.headers().get("apns-collapse-id");
+
+        assert_eq!(None, apns_collapse_id);
+    }
+
+    #[test]
+    fn test_request_with_an_apns_collapse_id() {
+        let builder = DefaultNotificationBuilder::new();
+
+        let payload = builder.build(
+            "a_test_id",
+            NotificationOptions {
+                apns_collapse_id: Some(CollapseId::new("a_collapse_id").unwrap()),
+                ..Default::default()
+            },
+        );
+
+        let client = Client::builder().build().unwrap();
+        let request = client.build_request(&payload).unwrap();
+        let apns_collapse_id = request.headers().get("apns-collapse-id").unwrap();
+
+        assert_eq!("a_collapse_id", apns_collapse_id);
+    }
+
+    #[test]
+    fn test_request_with_a_too_long_apns_collapse_id() {
+        let longest = "a".repeat(64);
+        // 63 bytes and a two-byte character
+        let multibyte = format!("{}é", "a".repeat(63));
+        let too_long = "a".repeat(65);
+
+        let client = Client::builder().build().unwrap();
+        let request = |value| {
+            let payload = DefaultNotificationBuilder::new().badge(1).build(
+                "a_test_id",
+                NotificationOptions {
+                    // Bypasses the check of CollapseId::new
+                    apns_collapse_id: Some(CollapseId { value }),
+                    ..Default::default()
+                },
+            );
+
+            client.build_request(&payload)
+        };
+
+        assert!(request(&longest).is_ok());
+        assert!(matches!(request(&multibyte), Err(Error::InvalidOptions(_))));
+        assert!(matches!(request(&too_long), Err(Error::InvalidOptions(_))));
+
+        let mut headers = RequestHeaders::default();
+        headers.insert("apns-collapse-id", too_long.clone());
+        let payload = DefaultNotificationBuilder::new()
+            .badge(1)
+            .build("a_test_id", Default::default());
+
+        assert!(matches!(
+            client.build_request_with_headers(&payload, Some(headers)),
+            Err(Error::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn test_request_with_client_default_options() {
+        let client = Client::builder()
+            .config(ClientConfig {
+                default_options: NotificationOptions {
+                    apns_topic: Some("com.example.default"),
+                    apns_priority: Some(Priority::Normal),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let payload = DefaultNotificationBuilder::new().build("a_test_id", Default::default());
+        let request = client.build_request(&payload).unwrap();
+
+        assert_eq!("com.example.default", request.headers().get("apns-topic").unwrap());
+        assert_eq!("5", request.headers().get("apns-priority").unwrap());
+
+        let payload = DefaultNotificationBuilder::new().build(
+            "a_test_id",
+            NotificationOptions {
+                apns_topic: Some("com.example.app"),
+                ..Default::default()
+            },
+        );
+        let request = client.build_request(&payload).unwrap();
+
+        assert_eq!("com.example.app", request.headers().get("apns-topic").unwrap());
+        assert_eq!("5", request.headers().get("apns-priority").unwrap());
+    }
+
+    #[test]
+    fn test_request_with_topic_for_push_type() {
+        use crate::request::notification::VoipNotificationBuilder;
+
+        let client = Client::builder()
+            .config(ClientConfig {
+                default_options: NotificationOptions {
+                    apns_topic: Some("com.example.default"),
+                    ..Default::default()
+                },
+                topic_for: HashMap::from([
+                    (PushType::Alert, String::from("com.example.app")),
+                    (PushType::Voip, String::from("com.example.app.voip")),
+                ]),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let topic = |payload: &Payload| {
+            let request = client.build_request(payload).unwrap();
+            request
+                .headers()
+                .get("apns-topic")
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_owned()
+        };
 
-        let builder = DefaultNotificationBuilder::new();
-        let payload = builder.build("a_test_id", Default::default());
-        let client = Client::builder().signer(signer).build().unwrap();
-        let request = client.build_request(payload).unwrap();
+        let alert = DefaultNotificationBuilder::new()
+            .title("a title")
+            .build("a_test_id", Default::default());
+        let voip = VoipNotificationBuilder::new().build("a_test_id", Default::default());
+        let background = DefaultNotificationBuilder::new()
+            .content_available()
+            .build("a_test_id", Default::default());
+        let explicit = DefaultNotificationBuilder::new().title("a title").build(
+            "a_test_id",
+            NotificationOptions {
+                apns_topic: Some("com.example.other"),
+                ..Default::default()
+            },
+        );
 
-        assert_ne!(None, request.headers().get(AUTHORIZATION));
+        assert_eq!("com.example.app", topic(&alert));
+        assert_eq!("com.example.app.voip", topic(&voip));
+        assert_eq!("com.example.default", topic(&background));
+        assert_eq!("com.example.other", topic(&explicit));
     }
 
     #[test]
-    fn test_request_with_background_type() {
-        let builder = DefaultNotificationBuilder::new();
-        let options = NotificationOptions {
-            apns_push_type: Some(PushType::Background),
-            ..Default::default()
+    fn test_strict_push_type_inference() {
+        let topic_for = HashMap::from([
+            (PushType::Alert, String::from("com.example.app")),
+            (PushType::Background, String::from("com.example.background")),
+        ]);
+        let payload = DefaultNotificationBuilder::new()
+            .content_available()
+            .mutable_content()
+            .build("a_test_id", Default::default());
+
+        let topic = |strict_push_type_inference| {
+            let client = Client::builder()
+                .config(ClientConfig {
+                    topic_for: topic_for.clone(),
+                    strict_push_type_inference,
+                    ..Default::default()
+                })
+                .build()
+                .unwrap();
+
+            let request = client.build_request(&payload).unwrap();
+            request
+                .headers()
+                .get("apns-topic")
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_owned()
         };
-        let payload = builder.build("a_test_id", options);
-        let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
-        let apns_push_type = request.headers().get("apns-push-type").unwrap();
 
-        assert_eq!("background", apns_push_type);
+        assert_eq!("com.example.background", topic(false));
+        assert_eq!("com.example.app", topic(true));
     }
 
     #[test]
-    fn test_request_with_default_priority() {
+    fn test_request_with_default_apns_topic() {
         let builder = DefaultNotificationBuilder::new();
+
         let payload = builder.build("a_test_id", Default::default());
+
         let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
-        let apns_priority = request.headers().get("apns-priority");
+        let request = client.build_request(&payload).unwrap();
+        let apns_topic = request.headers().get("apns-topic");
 
-        assert_eq!(None, apns_priority);
+        assert_eq!(None, apns_topic);
     }
 
     #[test]
-    fn test_request_with_normal_priority() {
+    fn test_request_with_an_apns_topic() {
         let builder = DefaultNotificationBuilder::new();
 
         let payload = builder.build(
             "a_test_id",
             NotificationOptions {
-                apns_priority: Some(Priority::Normal),
+                apns_topic: Some("com.example.app"),
                 ..Default::default()
             },
         );
 
         let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
-        let apns_priority = request.headers().get("apns-priority").unwrap();
+        let request = client.build_request(&payload).unwrap();
+        let apns_topic = request.headers().get("apns-topic").unwrap();
 
-        assert_eq!("5", apns_priority);
+        assert_eq!("com.example.app", apns_topic);
     }
 
     #[test]
-    fn test_request_with_high_priority() {
-        let builder = DefaultNotificationBuilder::new();
+    fn test_request_with_builder_headers() {
+        struct LiveActivityBuilder;
 
-        let payload = builder.build(
+        impl<'a> NotificationBuilder<'a> for LiveActivityBuilder {
+            fn build(self, target: impl Into<Target<'a>>, options: NotificationOptions<'a>) -> Payload<'a> {
+                DefaultNotificationBuilder::new()
+                    .event("update")
+                    .timestamp(1234)
+                    .build(target, options)
+            }
+
+            fn build_request(
+                self,
+                target: impl Into<Target<'a>>,
+                options: NotificationOptions<'a>,
+            ) -> (Payload<'a>, RequestHeaders) {
+                let payload = self.build(target, options);
+                let mut headers = RequestHeaders::new(&payload.options, &payload);
+                headers.insert("apns-push-type", "liveactivity");
+
+                (payload, headers)
+            }
+        }
+
+        let (payload, headers) = LiveActivityBuilder.build_request(
             "a_test_id",
             NotificationOptions {
-                apns_priority: Some(Priority::High),
+                apns_topic: Some("com.example.app.push-type.liveactivity"),
                 ..Default::default()
             },
         );
-
         let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
-        let apns_priority = request.headers().get("apns-priority").unwrap();
+        let request = client.build_request_with_headers(&payload, Some(headers)).unwrap();
 
-        assert_eq!("10", apns_priority);
+        assert_eq!("liveactivity", request.headers().get("apns-push-type").unwrap());
+        assert_eq!(
+            "com.example.app.push-type.liveactivity",
+            request.headers().get("apns-topic").unwrap()
+        );
     }
 
-    #[test]
-    fn test_request_with_default_apns_id() {
-        let builder = DefaultNotificationBuilder::new();
-
-        let payload = builder.build("a_test_id", Default::default());
+    #[tokio::test]
+    async fn test_request_with_on_serialize() {
+        let client = Client::builder()
+            .config(ClientConfig {
+                on_serialize: Some(Arc::new(|value: &mut serde_json::Value| {
+                    value["trace-id"] = serde_json::Value::from("a-trace-id");
+                })),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
 
-        let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
-        let apns_id = request.headers().get("apns-id");
+        let payload = DefaultNotificationBuilder::new().build("a_test_id", Default::default());
+        let request = client.build_request(&payload).unwrap();
+        let body = request.into_body().collect().await.unwrap().to_bytes();
 
-        assert_eq!(None, apns_id);
+        assert_eq!(
+            "{\"aps\":{\"mutable-content\":0},\"trace-id\":\"a-trace-id\"}",
+            String::from_utf8_lossy(&body)
+        );
     }
 
-    #[test]
-    fn test_request_with_an_apns_id() {
-        let builder = DefaultNotificationBuilder::new();
-
-        let payload = builder.build(
-            "a_test_id",
-            NotificationOptions {
-                apns_id: Some("a-test-apns-id"),
+    #[tokio::test]
+    async fn test_request_with_minimized_payload() {
+        let client = Client::builder()
+            .config(ClientConfig {
+                minimize_payload: true,
                 ..Default::default()
-            },
+            })
+            .build()
+            .unwrap();
+
+        let payload = DefaultNotificationBuilder::new()
+            .title("a title")
+            .badge(0)
+            .build("a_test_id", Default::default());
+        let request = client.build_request(&payload).unwrap();
+        let body = request.into_body().collect().await.unwrap().to_bytes();
+
+        assert_eq!(
+            "{\"aps\":{\"alert\":{\"title\":\"a title\"},\"badge\":0}}",
+            String::from_utf8_lossy(&body)
         );
+    }
 
+    #[test]
+    fn test_request_with_a_malformed_apns_topic() {
+        let builder = DefaultNotificationBuilder::new();
+        let options = NotificationOptions {
+            apns_topic: Some(" com.example.app"),
+            ..Default::default()
+        };
+        let payload = builder.build("a_test_id", options);
         let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
-        let apns_id = request.headers().get("apns-id").unwrap();
+        let request = client.build_request(&payload);
 
-        assert_eq!("a-test-apns-id", apns_id);
+        assert!(matches!(request, Err(Error::InvalidTopic(_))));
     }
 
     #[test]
-    fn test_request_with_default_apns_expiration() {
-        let builder = DefaultNotificationBuilder::new();
+    fn test_request_with_payload_over_the_limit() {
+        let client = Client::builder()
+            .config(ClientConfig {
+                max_payload_bytes: HashMap::from([(PushType::Alert, 64)]),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
 
-        let payload = builder.build("a_test_id", Default::default());
+        let payload = DefaultNotificationBuilder::new()
+            .body("a body long enough to exceed the configured limit")
+            .build("a_test_id", Default::default());
 
-        let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
-        let apns_expiration = request.headers().get("apns-expiration");
+        assert!(matches!(
+            client.build_request(&payload),
+            Err(Error::PayloadTooLarge { limit: 64, .. })
+        ));
 
-        assert_eq!(None, apns_expiration);
+        let payload = DefaultNotificationBuilder::new()
+            .custom_data_many([("call", json!("data long enough to exceed the configured limit"))])
+            .build(
+                "a_test_id",
+                NotificationOptions {
+                    apns_push_type: Some(PushType::Voip),
+                    ..Default::default()
+                },
+            );
+
+        assert!(client.build_request(&payload).is_ok());
+
+        let payload = DefaultNotificationBuilder::new()
+            .body("a".repeat(4096))
+            .build("a_test_id", Default::default());
+
+        assert!(matches!(
+            Client::builder().build().unwrap().build_request(&payload),
+            Err(Error::PayloadTooLarge { limit: 4096, .. })
+        ));
     }
 
     #[test]
-    fn test_request_with_an_apns_expiration() {
+    fn test_channel_request_uri() {
         let builder = DefaultNotificationBuilder::new();
-
         let payload = builder.build(
-            "a_test_id",
+            Target::Channel("dHN0LXNyY2gtY2hubA==".into()),
             NotificationOptions {
-                apns_expiration: Some(420),
+                apns_topic: Some("com.example.app"),
                 ..Default::default()
             },
         );
-
         let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
-        let apns_expiration = request.headers().get("apns-expiration").unwrap();
+        let request = client.build_request(&payload).unwrap();
+        let uri = format!("{}", request.uri());
+        let apns_channel_id = request.headers().get("apns-channel-id").unwrap();
 
-        assert_eq!("420", apns_expiration);
+        assert_eq!("https://api.push.apple.com/4/broadcasts/apps/com.example.app", &uri);
+        assert_eq!("dHN0LXNyY2gtY2hubA==", apns_channel_id);
     }
 
     #[test]
-    fn test_request_with_default_apns_collapse_id() {
-        let builder = DefaultNotificationBuilder::new();
+    fn test_channel_management_requests() {
+        let channel_id = ChannelId::new("dHN0LXNyY2gtY2hubA==").unwrap();
+        let client = Client::builder()
+            .config(ClientConfig {
+                endpoint: Endpoint::Sandbox,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
 
-        let payload = builder.build("a_test_id", Default::default());
+        let request = client
+            .build_channel_request(
+                "com.example.app",
+                &ChannelOperation::Create(MessageStoragePolicy::MostRecent),
+            )
+            .unwrap();
 
-        let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
-        let apns_collapse_id = request.headers().get("apns-collapse-id");
+        assert_eq!(
+            "https://api-manage-broadcast.sandbox.push.apple.com:2195/1/apps/com.example.app/channels",
+            &request.uri().to_string()
+        );
+        assert_eq!(&Method::POST, request.method());
+        assert!(request.headers().get("apns-channel-id").is_none());
 
-        assert_eq!(None, apns_collapse_id);
+        let request = client
+            .build_channel_request("com.example.app", &ChannelOperation::Read(channel_id.clone()))
+            .unwrap();
+
+        assert_eq!(&Method::GET, request.method());
+        assert_eq!(
+            "dHN0LXNyY2gtY2hubA==",
+            request.headers().get("apns-channel-id").unwrap()
+        );
+
+        let request = client
+            .build_channel_request("com.example.app", &ChannelOperation::Delete(channel_id))
+            .unwrap();
+
+        assert_eq!(&Method::DELETE, request.method());
+        assert_eq!(
+            "dHN0LXNyY2gtY2hubA==",
+            request.headers().get("apns-channel-id").unwrap()
+        );
     }
 
     #[test]
-    fn test_request_with_an_apns_collapse_id() {
-        let builder = DefaultNotificationBuilder::new();
+    fn test_channel_from_response() {
+        let create = || ChannelOperation::Create(MessageStoragePolicy::NoStorage);
+        let mut headers = HeaderMap::new();
+        headers.insert("apns-request-id", HeaderValue::from_static("a-request-id"));
 
-        let payload = builder.build(
-            "a_test_id",
-            NotificationOptions {
-                apns_collapse_id: Some(CollapseId::new("a_collapse_id").unwrap()),
-                ..Default::default()
+        assert!(matches!(
+            channel_from_response(create(), StatusCode::CREATED, &headers, b""),
+            Err(Error::InvalidResponse(_))
+        ));
+        assert!(matches!(
+            channel_from_response(create(), StatusCode::BAD_REQUEST, &headers, br#"{"reason":"BadTopic"}"#),
+            Err(Error::ResponseError(_))
+        ));
+
+        headers.insert("apns-channel-id", HeaderValue::from_static("dHN0LXNyY2gtY2hubA=="));
+
+        assert_eq!(
+            Channel {
+                channel_id: String::from("dHN0LXNyY2gtY2hubA=="),
+                message_storage_policy: Some(MessageStoragePolicy::NoStorage),
+                apns_request_id: Some(String::from("a-request-id")),
             },
+            channel_from_response(create(), StatusCode::CREATED, &headers, b"").unwrap()
         );
+    }
 
+    #[test]
+    fn test_channel_request_without_topic() {
+        let builder = DefaultNotificationBuilder::new();
+        let payload = builder.build(Target::Channel("dHN0LXNyY2gtY2hubA==".into()), Default::default());
         let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
-        let apns_collapse_id = request.headers().get("apns-collapse-id").unwrap();
+        let request = client.build_request(&payload);
 
-        assert_eq!("a_collapse_id", apns_collapse_id);
+        assert!(matches!(request, Err(Error::InvalidOptions(_))));
     }
 
     #[test]
-    fn test_request_with_default_apns_topic() {
+    fn test_device_request_without_channel_id() {
         let builder = DefaultNotificationBuilder::new();
-
         let payload = builder.build("a_test_id", Default::default());
-
         let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
-        let apns_topic = request.headers().get("apns-topic");
+        let request = client.build_request(&payload).unwrap();
 
-        assert_eq!(None, apns_topic);
+        assert_eq!(None, request.headers().get("apns-channel-id"));
     }
 
-    #[test]
-    fn test_request_with_an_apns_topic() {
-        let builder = DefaultNotificationBuilder::new();
+    #[tokio::test]
+    async fn test_max_inflight_shared_by_clones() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
 
-        let payload = builder.build(
-            "a_test_id",
-            NotificationOptions {
-                apns_topic: Some("a_topic"),
+        let client = Client::builder()
+            .config(ClientConfig {
+                max_inflight: Some(100),
                 ..Default::default()
-            },
-        );
+            })
+            .build()
+            .unwrap();
 
-        let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload).unwrap();
-        let apns_topic = request.headers().get("apns-topic").unwrap();
+        let inflight = Arc::new(AtomicUsize::new(0));
+        let max_seen = Arc::new(AtomicUsize::new(0));
+
+        let tasks = (0..1000)
+            .map(|_| {
+                let client = client.clone();
+                let inflight = inflight.clone();
+                let max_seen = max_seen.clone();
 
-        assert_eq!("a_topic", apns_topic);
+                tokio::spawn(async move {
+                    let _permit = client.acquire_inflight_permit().await;
+                    let current = inflight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_seen.fetch_max(current, Ordering::SeqCst);
+                    tokio::task::yield_now().await;
+                    inflight.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert!(max_seen.load(Ordering::SeqCst) <= 100);
     }
 
     #[tokio::test]
@@ -706,7 +3991,7 @@ jDwmlD1Gg0yJt1e38djFwsxsfr5q2hv0Rj9fTEqAPr8H7mGm0wKxZ7iQ
         let builder = DefaultNotificationBuilder::new();
         let payload = builder.build("a_test_id", Default::default());
         let client = Client::builder().build().unwrap();
-        let request = client.build_request(payload.clone()).unwrap();
+        let request = client.build_request(&payload).unwrap();
 
         let body = request.into_body().collect().await.unwrap().to_bytes();
         let body_str = String::from_utf8(body.to_vec()).unwrap();
@@ -714,6 +3999,142 @@ jDwmlD1Gg0yJt1e38djFwsxsfr5q2hv0Rj9fTEqAPr8H7mGm0wKxZ7iQ
         assert_eq!(payload.to_json_string().unwrap(), body_str,);
     }
 
+    #[test]
+    fn test_build_client_tls_config() {
+        let pfx = include_bytes!("../test_cert/test.p12");
+
+        let config = build_client_tls_config(pfx, "test").unwrap();
+        assert!(config.client_auth_cert_resolver.has_certs());
+
+        let result = build_client_tls_config(pfx, "wrong password");
+        assert!(result.is_err());
+    }
+
+    /// Accepts any certificate, leaving only the pins to check
+    #[derive(Debug)]
+    struct AcceptAnyCert;
+
+    impl rustls::client::danger::ServerCertVerifier for AcceptAnyCert {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &rustls_pki_types::CertificateDer<'_>,
+            _intermediates: &[rustls_pki_types::CertificateDer<'_>],
+            _server_name: &rustls_pki_types::ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: rustls_pki_types::UnixTime,
+        ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+            Ok(rustls::client::danger::ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            _message: &[u8],
+            _cert: &rustls_pki_types::CertificateDer<'_>,
+            _dss: &rustls::DigitallySignedStruct,
+        ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+            Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            _message: &[u8],
+            _cert: &rustls_pki_types::CertificateDer<'_>,
+            _dss: &rustls::DigitallySignedStruct,
+        ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+            Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+            crypto_provider().signature_verification_algorithms.supported_schemes()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handshake_with_pin_mismatch() {
+        use rustls_pki_types::pem::PemObject;
+        use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+
+        let cert = CertificateDer::from_pem_slice(include_bytes!("../test_cert/test.crt")).unwrap();
+        let key = PrivateKeyDer::from_pem_slice(include_bytes!("../test_cert/test.key")).unwrap();
+        let mut server_config = rustls::ServerConfig::builder_with_provider(crypto_provider())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert], key)
+            .unwrap();
+        server_config.alpn_protocols = vec![b"h2".to_vec()];
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Runs the server side of one TLS handshake, which the client aborts
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut conn = rustls::ServerConnection::new(Arc::new(server_config)).unwrap();
+
+            while conn.is_handshaking() {
+                if conn.complete_io(&mut stream).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let builder = Client::builder().config(ClientConfig {
+            resolve_override: Some(addr),
+            pinned_spki: Some(vec![vec![0; 32]]),
+            ..Default::default()
+        });
+        let verifier = RecordingVerifier::new(Arc::new(AcceptAnyCert), builder.connections.clone());
+        let tls_config = rustls::ClientConfig::builder_with_provider(crypto_provider())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth();
+        let client = builder
+            .connector(tls_connector(tls_config, None, Some(addr), false))
+            .build()
+            .unwrap();
+
+        let payload = DefaultNotificationBuilder::new()
+            .body("test")
+            .build("a_test_id", Default::default());
+        let result = client.send(payload).await;
+
+        server.join().unwrap();
+
+        assert!(
+            matches!(result, Err(Error::CertificatePinMismatch(ref host)) if host == Endpoint::Production.host()),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_load_identity() {
+        let pfx = include_bytes!("../test_cert/test.p12");
+        let key = include_bytes!("../test_cert/test.key");
+        let cert = include_bytes!("../test_cert/test.crt");
+        let bundle = [&cert[..], &key[..]].concat();
+
+        let (pfx_cert, pfx_key) = load_identity(pfx, Some("test")).unwrap();
+        let (bundle_cert, bundle_key) = load_identity(&bundle, None).unwrap();
+        let der = |pem: &[u8]| {
+            use rustls_pki_types::pem::{PemObject, SectionKind};
+
+            <(SectionKind, Vec<u8>)>::pem_slice_iter(pem)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+
+        assert_eq!(der(&pfx_cert), der(&bundle_cert));
+        assert_eq!(der(&pfx_key), der(&bundle_key));
+        assert!(client_cert_config(&bundle_cert, &bundle_key, None).is_ok());
+        assert!(Client::certificate(&mut &bundle[..], "", ClientConfig::default()).is_ok());
+
+        assert!(matches!(load_identity(cert, None), Err(Error::InvalidCertificate)));
+        assert!(load_identity(pfx, Some("wrong password")).is_err());
+    }
+
     #[tokio::test]
     /// Try to create a test client using the unencrypted key & cert provided.
     /// These are test values that do not work with Apple, but mimic the sort
@@ -724,6 +4145,75 @@ jDwmlD1Gg0yJt1e38djFwsxsfr5q2hv0Rj9fTEqAPr8H7mGm0wKxZ7iQ
 
         let c = Client::certificate_parts(&cert, &key, ClientConfig::default())?;
         assert!(c.options.signer.is_none());
+        assert_eq!(
+            Some(OffsetDateTime::from_unix_timestamp(2000153391).unwrap()),
+            c.client_cert_not_after()
+        );
         Ok(())
     }
+
+    #[test]
+    fn test_http_request() {
+        let signer = Signer::new(
+            PRIVATE_KEY.as_bytes(),
+            "89AFRD1X22",
+            "ASDFQWERTY",
+            Duration::from_secs(100),
+            Arc::new(SystemClock),
+        )
+        .unwrap();
+
+        let client = Client::builder()
+            .signer(signer)
+            .config(ClientConfig {
+                default_options: NotificationOptions {
+                    apns_topic: Some("com.example.default"),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("apns-push-type", HeaderValue::from_static("alert"));
+
+        let body = Bytes::from_static(b"{\"aps\":{\"alert\":\"Hi\"}}");
+        let request = client.build_http_request(headers, body.clone(), "a_test_id").unwrap();
+
+        assert_eq!(
+            "https://api.push.apple.com/3/device/a_test_id",
+            &request.uri().to_string()
+        );
+        assert_eq!("alert", request.headers().get("apns-push-type").unwrap());
+        assert_eq!("com.example.default", request.headers().get("apns-topic").unwrap());
+        assert_eq!("application/json", request.headers().get(CONTENT_TYPE).unwrap());
+        assert_eq!("22", request.headers().get(CONTENT_LENGTH).unwrap());
+        assert!(request.headers().contains_key(AUTHORIZATION));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("apns-topic", HeaderValue::from_static("com.example.app"));
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("bearer from-the-gateway"));
+
+        let request = client.build_http_request(headers, body, "a_test_id").unwrap();
+
+        assert_eq!("com.example.app", request.headers().get("apns-topic").unwrap());
+        assert_eq!("bearer from-the-gateway", request.headers().get(AUTHORIZATION).unwrap());
+
+        let client = Client::builder()
+            .config(ClientConfig {
+                default_options: NotificationOptions {
+                    apns_topic: Some("com.example.app\n"),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        assert!(matches!(
+            client.build_http_request(HeaderMap::new(), Bytes::new(), "a_test_id"),
+            Err(Error::BuildRequestError(_))
+        ));
+    }
 }
@@ -0,0 +1,54 @@
+//! The time source used for signing authentication tokens
+
+use std::fmt;
+#[cfg(any(test, feature = "test-util"))]
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+#[cfg(any(test, feature = "test-util"))]
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Provides the current time to the client.
+///
+/// The default [`SystemClock`] uses the system time. A custom clock can be set
+/// with [`ClientConfig::clock`](crate::ClientConfig::clock), e.g. to control
+/// when authentication tokens are renewed in tests.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// The current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// The system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when advanced, so tests can control the
+/// issued-at time of authentication tokens.
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug)]
+pub struct ManualClock(AtomicU64);
+
+#[cfg(any(test, feature = "test-util"))]
+impl ManualClock {
+    /// A clock standing at the given seconds since the Unix epoch.
+    pub fn new(unix_secs: u64) -> Self {
+        Self(AtomicU64::new(unix_secs))
+    }
+
+    /// Moves the clock forward by whole seconds of `duration`.
+    pub fn advance(&self, duration: Duration) {
+        self.0.fetch_add(duration.as_secs(), Ordering::SeqCst);
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.0.load(Ordering::SeqCst))
+    }
+}
@@ -0,0 +1,257 @@
+//! Tracking of the connections to APNs
+
+use crate::client::PeerInfo;
+use parking_lot::Mutex;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::{CertificateError, DigitallySignedStruct, OtherError, SignatureScheme};
+use rustls_pki_types::{CertificateDer, ServerName, UnixTime};
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::{fmt, io};
+
+/// Remembers the last connection used for every host and the certificate
+/// chain it presented. Shared by all clones of a client.
+///
+/// HTTP/2 sends all requests to a host over one connection, so a different
+/// local address means the connection was opened anew.
+#[derive(Debug, Default)]
+pub(crate) struct ConnectionTracker {
+    addrs: Mutex<HashMap<String, (SocketAddr, SocketAddr)>>,
+    cert_chains: Mutex<HashMap<String, Vec<String>>>,
+    pinned_spki: Option<Vec<Vec<u8>>>,
+}
+
+impl ConnectionTracker {
+    /// A tracker rejecting certificate chains without one of the SHA-256
+    /// hashes of a subject public key info in `pinned_spki`.
+    pub(crate) fn new(pinned_spki: Option<Vec<Vec<u8>>>) -> Self {
+        Self {
+            pinned_spki,
+            ..Default::default()
+        }
+    }
+
+    /// Records the local and remote address of the connection used for a
+    /// request to `host`, returning whether it differs from the connection
+    /// used before. Without known addresses the connection is assumed to be
+    /// reused.
+    pub(crate) fn reconnected(&self, host: &str, addrs: Option<(SocketAddr, SocketAddr)>) -> bool {
+        let Some(addrs) = addrs else {
+            return false;
+        };
+
+        self.addrs
+            .lock()
+            .insert(host.to_owned(), addrs)
+            .is_none_or(|(local_addr, _)| local_addr != addrs.0)
+    }
+
+    /// Records the subjects of the certificate chain the server presented
+    /// when connecting to `host`, leaf first.
+    pub(crate) fn record_cert_chain(&self, host: &str, subjects: Vec<String>) {
+        self.cert_chains.lock().insert(host.to_owned(), subjects);
+    }
+
+    /// Checks a certificate chain against the pinned keys.
+    fn check_pins(&self, chain: &[x509_parser::certificate::X509Certificate<'_>]) -> bool {
+        let Some(ref pinned_spki) = self.pinned_spki else {
+            return true;
+        };
+
+        chain
+            .iter()
+            .filter_map(|cert| sha256(cert.tbs_certificate.subject_pki.raw))
+            .any(|hash| pinned_spki.contains(&hash))
+    }
+
+    /// The peer of the last connection to `host`.
+    pub(crate) fn peer_info(&self, host: &str) -> Option<PeerInfo> {
+        let (_, addr) = *self.addrs.lock().get(host)?;
+        let cert_chain_summary = self.cert_chains.lock().get(host).cloned().unwrap_or_default();
+
+        Some(PeerInfo {
+            addr,
+            cert_chain_summary,
+        })
+    }
+}
+
+/// Verifies server certificates with another verifier, recording the
+/// certificate chains passing the verification.
+#[derive(Debug)]
+pub(crate) struct RecordingVerifier {
+    inner: Arc<dyn ServerCertVerifier>,
+    connections: Arc<ConnectionTracker>,
+}
+
+impl RecordingVerifier {
+    pub(crate) fn new(inner: Arc<dyn ServerCertVerifier>, connections: Arc<ConnectionTracker>) -> Self {
+        Self { inner, connections }
+    }
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self
+            .inner
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)?;
+
+        let host = server_name.to_str();
+        let chain = std::iter::once(end_entity)
+            .chain(intermediates)
+            .filter_map(|cert| x509_parser::parse_x509_certificate(cert).ok())
+            .map(|(_, cert)| cert)
+            .collect::<Vec<_>>();
+
+        if !self.connections.check_pins(&chain) {
+            let mismatch = PinMismatch {
+                host: host.into_owned(),
+            };
+
+            return Err(rustls::Error::InvalidCertificate(CertificateError::Other(OtherError(
+                Arc::new(mismatch),
+            ))));
+        }
+
+        let subjects = chain.iter().map(|cert| cert.subject().to_string()).collect();
+
+        self.connections.record_cert_chain(&host, subjects);
+
+        Ok(verified)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// The TLS handshake error for a server presenting no pinned certificate,
+/// carried through the connector to the failed request.
+#[derive(Debug)]
+pub(crate) struct PinMismatch {
+    pub(crate) host: String,
+}
+
+impl fmt::Display for PinMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The certificate of {} doesn't match any pinned key", self.host)
+    }
+}
+
+impl StdError for PinMismatch {}
+
+/// Finds a [`PinMismatch`] in the sources of a connection error. The
+/// connector wraps the TLS error in I/O errors, which don't expose it as
+/// their source.
+pub(crate) fn find_pin_mismatch<'a>(e: &'a (dyn StdError + 'static)) -> Option<&'a PinMismatch> {
+    if let Some(mismatch) = e.downcast_ref::<PinMismatch>() {
+        return Some(mismatch);
+    }
+
+    if let Some(rustls::Error::InvalidCertificate(CertificateError::Other(OtherError(e)))) =
+        e.downcast_ref::<rustls::Error>()
+    {
+        return find_pin_mismatch(e.as_ref());
+    }
+
+    if let Some(inner) = e.downcast_ref::<io::Error>().and_then(io::Error::get_ref) {
+        return find_pin_mismatch(inner);
+    }
+
+    e.source().and_then(find_pin_mismatch)
+}
+
+/// The SHA-256 hash of `data`, using the crypto provider of the client.
+fn sha256(data: &[u8]) -> Option<Vec<u8>> {
+    use rustls::crypto::hash::HashAlgorithm;
+
+    crate::client::crypto_provider()
+        .cipher_suites
+        .iter()
+        .filter_map(|suite| suite.tls13())
+        .map(|suite| suite.common.hash_provider)
+        .find(|hash| hash.algorithm() == HashAlgorithm::SHA256)
+        .map(|hash| hash.hash(data).as_ref().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnected() {
+        let tracker = ConnectionTracker::default();
+        let remote: SocketAddr = "17.188.0.1:443".parse().unwrap();
+        let first: SocketAddr = "10.0.0.1:50000".parse().unwrap();
+        let second: SocketAddr = "10.0.0.1:50001".parse().unwrap();
+
+        assert!(tracker.reconnected("api.push.apple.com", Some((first, remote))));
+        assert!(!tracker.reconnected("api.push.apple.com", Some((first, remote))));
+        assert!(tracker.reconnected("api.push.apple.com", Some((second, remote))));
+        assert!(tracker.reconnected("api-manage-broadcast.push.apple.com", Some((first, remote))));
+        assert!(!tracker.reconnected("api.push.apple.com", Some((second, remote))));
+        assert!(!tracker.reconnected("api.push.apple.com", None));
+    }
+
+    #[test]
+    fn test_peer_info() {
+        let tracker = ConnectionTracker::default();
+        let remote: SocketAddr = "17.188.0.1:443".parse().unwrap();
+        let local: SocketAddr = "10.0.0.1:50000".parse().unwrap();
+
+        assert!(tracker.peer_info("api.push.apple.com").is_none());
+
+        tracker.record_cert_chain("api.push.apple.com", vec![String::from("CN=api.push.apple.com")]);
+        tracker.reconnected("api.push.apple.com", Some((local, remote)));
+
+        let peer_info = tracker.peer_info("api.push.apple.com").unwrap();
+
+        assert_eq!(remote, peer_info.addr);
+        assert_eq!(
+            vec![String::from("CN=api.push.apple.com")],
+            peer_info.cert_chain_summary
+        );
+    }
+
+    #[test]
+    fn test_check_pins() {
+        use rustls_pki_types::pem::PemObject;
+
+        let der = CertificateDer::from_pem_slice(include_bytes!("../test_cert/test.crt")).unwrap();
+        let (_, cert) = x509_parser::parse_x509_certificate(&der).unwrap();
+        let pin = sha256(cert.tbs_certificate.subject_pki.raw).unwrap();
+        let chain = [cert];
+
+        assert_eq!(32, pin.len());
+        assert!(ConnectionTracker::default().check_pins(&chain));
+        assert!(ConnectionTracker::new(Some(vec![vec![0; 32], pin])).check_pins(&chain));
+        assert!(!ConnectionTracker::new(Some(vec![vec![0; 32]])).check_pins(&chain));
+    }
+}
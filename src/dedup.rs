@@ -0,0 +1,121 @@
+//! Suppression of repeated sends of the same notification
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, SystemTime};
+
+/// Remembers recently sent notifications for a fixed window of time. Shared by
+/// all clones of a client.
+#[derive(Debug)]
+pub(crate) struct DedupCache {
+    window: Duration,
+    sent: Mutex<HashMap<u64, SystemTime>>,
+}
+
+impl DedupCache {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The key identifying a notification by its target, collapse ID and
+    /// serialized body.
+    pub(crate) fn key(target: &str, collapse_id: Option<&str>, body: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        target.hash(&mut hasher);
+        collapse_id.hash(&mut hasher);
+        body.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Records the notification as sent at `now` unless it was sent within
+    /// the window before, returning whether it may be sent. Checking and
+    /// recording under one lock keeps concurrent sends of the same
+    /// notification from both passing.
+    pub(crate) fn reserve(&self, key: u64, now: SystemTime) -> bool {
+        let mut sent = self.sent.lock();
+        sent.retain(|_, sent_at| elapsed(*sent_at, now) < self.window);
+
+        if sent.contains_key(&key) {
+            return false;
+        }
+
+        sent.insert(key, now);
+        true
+    }
+
+    /// Restarts the window of a reserved notification at `now`, when APNs
+    /// accepted it.
+    pub(crate) fn confirm(&self, key: u64, now: SystemTime) {
+        self.sent.lock().insert(key, now);
+    }
+
+    /// Forgets a reserved notification that couldn't be sent, so it may be
+    /// sent again.
+    pub(crate) fn release(&self, key: u64) {
+        self.sent.lock().remove(&key);
+    }
+}
+
+/// The time from `earlier` to `now`, zero if the clock went backwards.
+fn elapsed(earlier: SystemTime, now: SystemTime) -> Duration {
+    now.duration_since(earlier).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_within_window() {
+        let cache = DedupCache::new(Duration::from_secs(10));
+        let key = DedupCache::key("token", None, "{}");
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1672531200);
+
+        assert!(cache.reserve(key, now));
+        assert!(!cache.reserve(key, now));
+        assert!(!cache.reserve(key, now + Duration::from_secs(9)));
+
+        cache.confirm(key, now + Duration::from_secs(5));
+
+        assert!(!cache.reserve(key, now + Duration::from_secs(14)));
+        assert!(cache.reserve(key, now + Duration::from_secs(15)));
+    }
+
+    #[test]
+    fn test_released_key_is_sent_again() {
+        let cache = DedupCache::new(Duration::from_secs(10));
+        let now = SystemTime::UNIX_EPOCH;
+
+        assert!(cache.reserve(1, now));
+
+        cache.release(1);
+
+        assert!(cache.reserve(1, now));
+    }
+
+    #[test]
+    fn test_dedup_key() {
+        let key = DedupCache::key("token", Some("collapse"), "{}");
+
+        assert_eq!(key, DedupCache::key("token", Some("collapse"), "{}"));
+        assert_ne!(key, DedupCache::key("other", Some("collapse"), "{}"));
+        assert_ne!(key, DedupCache::key("token", None, "{}"));
+        assert_ne!(key, DedupCache::key("token", Some("collapse"), "{\"aps\":{}}"));
+    }
+
+    #[test]
+    fn test_expired_entries_are_removed() {
+        let cache = DedupCache::new(Duration::from_secs(10));
+        let now = SystemTime::UNIX_EPOCH;
+
+        cache.reserve(1, now);
+        cache.reserve(2, now + Duration::from_secs(10));
+
+        assert_eq!(1, cache.sent.lock().len());
+    }
+}
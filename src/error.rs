@@ -1,5 +1,6 @@
 /// Error and result module
-use crate::{response::Response, signer::SignerError};
+use crate::response::{ErrorReason, Response};
+use crate::signer::SignerError;
 use std::io;
 use thiserror::Error;
 
@@ -23,6 +24,12 @@ pub enum Error {
     /// APNs couldn't accept the notification. Contains
     /// [Response](response/struct.Response.html) with additional
     /// information.
+    ///
+    /// Rejections with status 403, 413 and 429 and undocumented statuses are
+    /// returned as [`Forbidden`](Self::Forbidden),
+    /// [`PayloadRejected`](Self::PayloadRejected),
+    /// [`TooManyRequests`](Self::TooManyRequests) and
+    /// [`Unexpected`](Self::Unexpected) instead.
     #[error(
         "Notification was not accepted by APNs (reason: {})",
         .0.error
@@ -32,11 +39,71 @@ pub enum Error {
     )]
     ResponseError(Response),
 
+    /// APNs rejected the authentication with status 403, e.g. because of an
+    /// invalid certificate or an expired token.
+    #[error(
+        "Request was forbidden by APNs (reason: {})",
+        .reason.as_ref().map(ToString::to_string).unwrap_or_else(|| "Unknown".to_string())
+    )]
+    Forbidden {
+        reason: Option<ErrorReason>,
+        response: Response,
+    },
+
+    /// APNs rejected the notification with status 413 because the payload
+    /// is too large.
+    #[error("The payload was rejected by APNs as too large")]
+    PayloadRejected(Response),
+
+    /// APNs rejected the notification with status 429 because too many were
+    /// sent to the same device token.
+    #[error("Too many notifications were sent to the device token")]
+    TooManyRequests(Response),
+
+    /// APNs answered with a status it doesn't document.
+    #[error("Unexpected status {status} from APNs")]
+    Unexpected { status: u16, response: Response },
+
     /// Invalid option values given in
     /// [NotificationOptions](request/notification/struct.NotificationOptions.html)
     #[error("Invalid options for APNs payload: {0}")]
     InvalidOptions(String),
 
+    /// The `apns_expiration` can't be represented as a UNIX timestamp.
+    #[error("Invalid apns-expiration: {0}")]
+    InvalidExpiration(String),
+
+    /// The payload contains fields that contradict each other.
+    #[error("Conflicting fields in APNs payload: {0}")]
+    ConflictingFields(String),
+
+    /// The `apns_topic` is not shaped like a bundle ID.
+    #[error("Invalid apns-topic: {0}")]
+    InvalidTopic(String),
+
+    /// The serialized payload exceeds the size limit for its push type, so
+    /// it wasn't sent. A payload APNs rejects with status 413 is returned as
+    /// [`PayloadRejected`](Self::PayloadRejected).
+    #[error("The payload is {size} bytes, exceeding the limit of {limit} bytes")]
+    PayloadTooLarge { size: usize, limit: usize },
+
+    /// A [`WebPushAlert`](crate::WebPushAlert) couldn't be parsed.
+    #[error("Invalid web push alert: {0}")]
+    InvalidWebPushAlert(String),
+
+    /// The serialized `attributes` of a Live Activity exceed
+    /// [`MAX_ATTRIBUTES_BYTES`](crate::request::payload::MAX_ATTRIBUTES_BYTES).
+    #[error("The Live Activity attributes are {size} bytes, exceeding the limit of {limit} bytes")]
+    AttributesTooLarge { size: usize, limit: usize },
+
+    /// APNs accepted the request, but its answer lacks data it documents.
+    #[error("Invalid response from APNs: {0}")]
+    InvalidResponse(String),
+
+    /// The device token or channel ID is malformed.
+    #[error("Invalid notification target: {0}")]
+    InvalidTarget(String),
+
     /// Error reading the certificate or private key.
     #[error("Error in reading a certificate file: {0}")]
     ReadError(#[from] io::Error),
@@ -59,6 +126,73 @@ pub enum Error {
 
     #[error("Invalid certificate")]
     InvalidCertificate,
+
+    /// The same notification is being sent or was accepted by APNs within
+    /// the [`ClientConfig::dedup_window`](crate::ClientConfig::dedup_window),
+    /// so it wasn't sent again.
+    #[error("The notification was already sent within the deduplication window")]
+    Duplicate,
+
+    /// The server presented no certificate matching
+    /// [`ClientConfig::pinned_spki`](crate::ClientConfig::pinned_spki).
+    #[error("The certificate of {0} doesn't match any pinned key")]
+    CertificatePinMismatch(String),
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        let kind = match e {
+            Error::ReadError(e) => return e,
+            Error::RequestTimeout(_) => io::ErrorKind::TimedOut,
+            Error::ConnectionError(ref e) if e.is_timeout() => io::ErrorKind::TimedOut,
+            Error::ConnectionError(_) => io::ErrorKind::ConnectionAborted,
+            Error::ClientError(ref e) if e.is_connect() => io::ErrorKind::ConnectionRefused,
+            Error::InvalidOptions(_)
+            | Error::InvalidExpiration(_)
+            | Error::ConflictingFields(_)
+            | Error::InvalidTarget(_)
+            | Error::InvalidTopic(_)
+            | Error::InvalidWebPushAlert(_)
+            | Error::PayloadTooLarge { .. }
+            | Error::PayloadRejected(_)
+            | Error::AttributesTooLarge { .. }
+            | Error::SerializeError(_)
+            | Error::BuildRequestError(_) => io::ErrorKind::InvalidInput,
+            Error::Tls(_) | Error::InvalidCertificate | Error::CertificatePinMismatch(_) => io::ErrorKind::InvalidData,
+            Error::Forbidden { .. } => io::ErrorKind::PermissionDenied,
+            _ => io::ErrorKind::Other,
+        };
+
+        io::Error::new(kind, e)
+    }
+}
+
+impl Error {
+    /// The error for a response APNs rejected.
+    pub(crate) fn from_response(response: Response) -> Self {
+        match response.code {
+            403 => Error::Forbidden {
+                reason: response.error.as_ref().map(|error| error.reason.clone()),
+                response,
+            },
+            413 => Error::PayloadRejected(response),
+            429 => Error::TooManyRequests(response),
+            400 | 404 | 405 | 410 | 500 | 503 => Error::ResponseError(response),
+            status => Error::Unexpected { status, response },
+        }
+    }
+
+    /// The response from APNs, if it rejected the request.
+    pub(crate) fn response(&self) -> Option<&Response> {
+        match self {
+            Error::ResponseError(response)
+            | Error::Forbidden { response, .. }
+            | Error::PayloadRejected(response)
+            | Error::TooManyRequests(response)
+            | Error::Unexpected { response, .. } => Some(response),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(all(not(feature = "ring"), feature = "openssl"))]
@@ -67,3 +201,69 @@ impl From<openssl::error::ErrorStack> for Error {
         Self::SignerError(SignerError::OpenSSL(e))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_io_error() {
+        let e = io::Error::from(Error::RequestTimeout(20));
+        assert_eq!(io::ErrorKind::TimedOut, e.kind());
+        assert!(matches!(
+            e.into_inner().unwrap().downcast::<Error>().as_deref(),
+            Ok(Error::RequestTimeout(20))
+        ));
+
+        let e = io::Error::from(Error::InvalidTopic(String::from("example")));
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+
+        let e = io::Error::from(Error::ReadError(io::Error::from(io::ErrorKind::NotFound)));
+        assert_eq!(io::ErrorKind::NotFound, e.kind());
+    }
+
+    #[test]
+    fn test_from_response() {
+        let response = |code, reason: Option<ErrorReason>| Response {
+            apns_id: None,
+            apns_unique_id: None,
+            error: reason.map(|reason| crate::response::ErrorBody {
+                reason,
+                timestamp: None,
+            }),
+            code,
+            delivery: None,
+            reconnected: false,
+        };
+
+        assert!(matches!(
+            Error::from_response(response(403, Some(ErrorReason::ExpiredProviderToken))),
+            Error::Forbidden {
+                reason: Some(ErrorReason::ExpiredProviderToken),
+                ..
+            }
+        ));
+        assert!(matches!(
+            Error::from_response(response(413, Some(ErrorReason::PayloadTooLarge))),
+            Error::PayloadRejected(_)
+        ));
+        assert!(matches!(
+            Error::from_response(response(429, Some(ErrorReason::TooManyRequests))),
+            Error::TooManyRequests(_)
+        ));
+        assert!(matches!(
+            Error::from_response(response(410, Some(ErrorReason::Unregistered))),
+            Error::ResponseError(_)
+        ));
+        assert!(matches!(
+            Error::from_response(response(502, None)),
+            Error::Unexpected { status: 502, .. }
+        ));
+
+        let e = Error::from_response(response(503, Some(ErrorReason::ServiceUnavailable)));
+        assert_eq!(Some(503), e.response().map(|response| response.code));
+
+        let e = io::Error::from(Error::from_response(response(403, None)));
+        assert_eq!(io::ErrorKind::PermissionDenied, e.kind());
+    }
+}
@@ -0,0 +1,87 @@
+//! Generation of `apns-id` values
+
+use crate::error::Error;
+use std::fmt;
+#[cfg(any(test, feature = "test-util"))]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Generates the `apns-id` of notifications sent without one.
+///
+/// Set with [`ClientConfig::apns_id_generator`](crate::ClientConfig::apns_id_generator)
+/// to know the ID of a notification before APNs answers. The IDs must be
+/// canonical UUIDs.
+pub trait IdGenerator: fmt::Debug + Send + Sync {
+    /// The ID for the next notification.
+    fn next_id(&self) -> Result<String, Error>;
+}
+
+/// Random version 4 UUIDs, using the randomness of the TLS crypto provider.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomIdGenerator;
+
+impl IdGenerator for RandomIdGenerator {
+    fn next_id(&self) -> Result<String, Error> {
+        let mut bytes = [0u8; 16];
+
+        crate::client::crypto_provider()
+            .secure_random
+            .fill(&mut bytes)
+            .map_err(|_| rustls::Error::FailedToGetRandomBytes)?;
+
+        // Version 4 and the RFC 4122 variant
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        Ok(format_uuid(u128::from_be_bytes(bytes)))
+    }
+}
+
+/// Counts up from `00000000-0000-0000-0000-000000000001`, so tests can
+/// assert the exact IDs sent.
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Default)]
+pub struct SequentialIdGenerator(AtomicU64);
+
+#[cfg(any(test, feature = "test-util"))]
+impl IdGenerator for SequentialIdGenerator {
+    fn next_id(&self) -> Result<String, Error> {
+        Ok(format_uuid(u128::from(self.0.fetch_add(1, Ordering::SeqCst) + 1)))
+    }
+}
+
+/// The canonical, hyphenated form of a UUID.
+fn format_uuid(uuid: u128) -> String {
+    let hex = format!("{uuid:032x}");
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_ids() {
+        let id = RandomIdGenerator.next_id().unwrap();
+
+        assert_eq!(36, id.len());
+        assert_eq!(Some('4'), id.chars().nth(14));
+        assert!(matches!(id.chars().nth(19), Some('8' | '9' | 'a' | 'b')));
+        assert_ne!(id, RandomIdGenerator.next_id().unwrap());
+    }
+
+    #[test]
+    fn test_sequential_ids() {
+        let generator = SequentialIdGenerator::default();
+
+        assert_eq!("00000000-0000-0000-0000-000000000001", generator.next_id().unwrap());
+        assert_eq!("00000000-0000-0000-0000-000000000002", generator.next_id().unwrap());
+    }
+}
@@ -119,22 +119,48 @@ extern crate serde;
 extern crate serde_json;
 
 pub mod client;
+pub mod clock;
+mod connections;
+mod dedup;
 pub mod error;
+pub mod id;
+pub mod notifier;
 #[cfg(feature = "ring")]
 mod pkcs12;
 pub mod request;
 pub mod response;
+pub mod retry;
 mod signer;
 
+pub use crate::request::channel::{Channel, ChannelOperation, MessageStoragePolicy};
+
+pub use crate::request::headers::RequestHeaders;
+
 pub use crate::request::notification::{
     CollapseId, DefaultNotificationBuilder, NotificationBuilder, NotificationOptions, Priority, PushType,
-    WebNotificationBuilder, WebPushAlert,
+    VoipNotificationBuilder, WebNotificationBuilder, WebPushAlert,
 };
 
-pub use crate::request::payload::InterruptionLevel;
+pub use crate::request::payload::{InterruptionLevel, TrimPolicy};
+
+pub use crate::request::target::{ChannelId, DeviceToken, Target};
+
+pub use crate::response::{
+    AuditRecord, BatchResponseItem, CampaignReport, Delivery, ErrorBody, ErrorReason, Response, VerboseResponse,
+};
+
+pub use crate::client::{Client, ClientConfig, Endpoint, H2Settings, PeerInfo, TokenAuth};
+
+pub use crate::clock::{Clock, SystemClock};
+
+#[cfg(feature = "test-util")]
+pub use crate::clock::ManualClock;
+
+pub use crate::id::{IdGenerator, RandomIdGenerator};
 
-pub use crate::response::{ErrorBody, ErrorReason, Response};
+pub use crate::notifier::{Notifier, NotifierBuilder};
 
-pub use crate::client::{Client, ClientConfig, Endpoint};
+#[cfg(feature = "test-util")]
+pub use crate::id::SequentialIdGenerator;
 
 pub use crate::error::Error;
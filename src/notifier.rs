@@ -0,0 +1,217 @@
+//! A client with defaults for sending notifications reliably
+
+use crate::client::{Client, ClientConfig, Endpoint};
+use crate::error::Error;
+use crate::request::notification::NotificationBuilder;
+use crate::response::Response;
+use crate::retry::ExponentialBackoff;
+use std::fmt;
+use std::io::Read;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The hook of [`NotifierBuilder::on_notify`]
+pub type NotifyFn = dyn Fn(&Result<Response, Error>, Duration) + Send + Sync;
+
+/// Sends notifications to device tokens, retrying failures that may pass and
+/// reporting tokens APNs no longer accepts.
+///
+/// Wraps a [`Client`] configured by a [`NotifierBuilder`]. Failed sends are
+/// retried with [`ExponentialBackoff`] unless configured otherwise.
+///
+/// ```no_run
+/// # use apns_h2::{DefaultNotificationBuilder, Notifier};
+/// # use std::fs::File;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let notifier = Notifier::builder()
+///     .topic("com.example.app")
+///     .on_invalid_token(|token, _| println!("Delete {token}"))
+///     .token(File::open("/path/to/private_key.p8")?, "KEY_ID", "TEAM_ID")?;
+///
+/// notifier.notify("device-token", DefaultNotificationBuilder::new().body("Hi there")).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Notifier {
+    client: Client,
+    on_notify: Option<Arc<NotifyFn>>,
+}
+
+impl fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Notifier")
+            .field("client", &self.client)
+            .field("on_notify", &self.on_notify.as_ref().map(|_| "Fn"))
+            .finish()
+    }
+}
+
+impl Notifier {
+    /// Creates a builder for a notifier sending to [`Endpoint::Production`].
+    pub fn builder() -> NotifierBuilder {
+        NotifierBuilder::default()
+    }
+
+    /// Builds the notification for the device `token` and sends it.
+    ///
+    /// See [ErrorReason](crate::ErrorReason) for possible errors.
+    pub async fn notify<'a, B: NotificationBuilder<'a>>(&self, token: &'a str, builder: B) -> Result<Response, Error> {
+        let started = Instant::now();
+        let result = self.client.send(builder.build(token, Default::default())).await;
+
+        if let Some(ref on_notify) = self.on_notify {
+            on_notify(&result, started.elapsed());
+        }
+
+        result
+    }
+
+    /// The client sending the notifications, e.g. for sending with other
+    /// options.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+}
+
+/// Configures a [`Notifier`], created with [`Notifier::builder`].
+pub struct NotifierBuilder {
+    config: ClientConfig,
+    on_notify: Option<Arc<NotifyFn>>,
+}
+
+impl Default for NotifierBuilder {
+    fn default() -> Self {
+        Self {
+            config: ClientConfig {
+                retry_policy: Some(Arc::new(ExponentialBackoff::default())),
+                ..Default::default()
+            },
+            on_notify: None,
+        }
+    }
+}
+
+impl fmt::Debug for NotifierBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NotifierBuilder")
+            .field("config", &self.config)
+            .field("on_notify", &self.on_notify.as_ref().map(|_| "Fn"))
+            .finish()
+    }
+}
+
+impl NotifierBuilder {
+    /// The APNs environment to send to, [`Endpoint::Production`] by default.
+    pub fn endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.config.endpoint = endpoint;
+        self
+    }
+
+    /// The topic of all notifications, usually the bundle ID of the app.
+    pub fn topic(mut self, topic: &'static str) -> Self {
+        self.config.default_options.apns_topic = Some(topic);
+        self
+    }
+
+    /// Retry failures that may pass up to `max_retries` times, 3 by default.
+    /// 0 disables retries.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.config.retry_policy = Some(Arc::new(ExponentialBackoff {
+            max_retries,
+            ..Default::default()
+        }));
+        self
+    }
+
+    /// Called with the device token when APNs reports it invalid, see
+    /// [`ClientConfig::on_invalid_token`].
+    pub fn on_invalid_token(mut self, on_invalid_token: impl Fn(&str, Option<u64>) + Send + Sync + 'static) -> Self {
+        self.config.on_invalid_token = Some(Arc::new(on_invalid_token));
+        self
+    }
+
+    /// Called with the result of every [`Notifier::notify`] and the time it
+    /// took including retries, e.g. to record metrics.
+    pub fn on_notify(mut self, on_notify: impl Fn(&Result<Response, Error>, Duration) + Send + Sync + 'static) -> Self {
+        self.on_notify = Some(Arc::new(on_notify));
+        self
+    }
+
+    /// Changes any other setting of the client.
+    pub fn config(mut self, configure: impl FnOnce(&mut ClientConfig)) -> Self {
+        configure(&mut self.config);
+        self
+    }
+
+    /// Creates the notifier, authenticating with a token signed by the
+    /// private key, see [`Client::token`].
+    pub fn token<S, T, R>(self, pkcs8_pem: R, key_id: S, team_id: T) -> Result<Notifier, Error>
+    where
+        S: Into<String>,
+        T: Into<String>,
+        R: Read,
+    {
+        let client = Client::token(pkcs8_pem, key_id, team_id, self.config)?;
+
+        Ok(Notifier {
+            client,
+            on_notify: self.on_notify,
+        })
+    }
+
+    /// Creates the notifier, authenticating with a PKCS#12 provider
+    /// certificate, see [`Client::certificate`].
+    pub fn certificate<R: Read>(self, certificate: &mut R, password: &str) -> Result<Notifier, Error> {
+        let client = Client::certificate(certificate, password, self.config)?;
+
+        Ok(Notifier {
+            client,
+            on_notify: self.on_notify,
+        })
+    }
+
+    /// Creates the notifier, authenticating with a PEM-encoded provider
+    /// certificate and key, see [`Client::certificate_parts`].
+    pub fn certificate_parts(self, cert_pem: &[u8], key_pem: &[u8]) -> Result<Notifier, Error> {
+        let client = Client::certificate_parts(cert_pem, key_pem, self.config)?;
+
+        Ok(Notifier {
+            client,
+            on_notify: self.on_notify,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::notification::DefaultNotificationBuilder;
+    use parking_lot::Mutex;
+
+    #[tokio::test]
+    async fn test_notify_reports_the_result() {
+        let key = include_bytes!("../test_cert/test.key");
+        let cert = include_bytes!("../test_cert/test.crt");
+        let results = Arc::new(Mutex::new(Vec::new()));
+
+        let notifier = Notifier::builder()
+            .endpoint(Endpoint::sandbox())
+            .topic(" not a topic")
+            .on_notify({
+                let results = results.clone();
+                move |result, _| results.lock().push(result.is_ok())
+            })
+            .certificate_parts(cert, key)
+            .unwrap();
+
+        let result = notifier
+            .notify("a_test_id", DefaultNotificationBuilder::new().body("Hi"))
+            .await;
+
+        assert!(matches!(result, Err(Error::InvalidTopic(_))));
+        assert_eq!(vec![false], *results.lock());
+        assert_eq!("sandbox", notifier.client().endpoint().name());
+    }
+}
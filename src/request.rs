@@ -1,4 +1,7 @@
 //! The request payload module
 
+pub mod channel;
+pub mod headers;
 pub mod notification;
 pub mod payload;
+pub mod target;
@@ -0,0 +1,94 @@
+/// Broadcast channel management
+use crate::request::target::ChannelId;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Whether APNs stores the most recent message of a broadcast channel for
+/// devices that are offline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageStoragePolicy {
+    /// Messages are only delivered to devices that are online.
+    NoStorage,
+    /// The most recent message is stored and delivered once the device comes
+    /// online.
+    MostRecent,
+}
+
+impl Serialize for MessageStoragePolicy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            MessageStoragePolicy::NoStorage => serializer.serialize_u8(0),
+            MessageStoragePolicy::MostRecent => serializer.serialize_u8(1),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MessageStoragePolicy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match u8::deserialize(deserializer)? {
+            0 => Ok(MessageStoragePolicy::NoStorage),
+            1 => Ok(MessageStoragePolicy::MostRecent),
+            other => Err(serde::de::Error::custom(format!(
+                "unknown message storage policy {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// An operation on the broadcast channels of an app, sent with
+/// [`Client::manage_channel`](crate::Client::manage_channel).
+#[derive(Debug, Clone)]
+pub enum ChannelOperation<'a> {
+    /// Creates a new channel for Live Activity broadcasts.
+    Create(MessageStoragePolicy),
+    /// Reads the configuration of an existing channel.
+    Read(ChannelId<'a>),
+    /// Deletes an existing channel.
+    Delete(ChannelId<'a>),
+}
+
+/// A broadcast channel, as returned by the channel management API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Channel {
+    /// The base64 encoded channel ID, to be used as a
+    /// [`Target::Channel`](crate::Target::Channel).
+    pub channel_id: String,
+
+    /// The storage policy of the channel. Only available when creating or
+    /// reading a channel.
+    pub message_storage_policy: Option<MessageStoragePolicy>,
+
+    /// The identifier APNs assigned to the management request.
+    pub apns_request_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct ChannelBody {
+    pub(crate) message_storage_policy: MessageStoragePolicy,
+    pub(crate) push_type: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_channel_body() {
+        let body = ChannelBody {
+            message_storage_policy: MessageStoragePolicy::MostRecent,
+            push_type: String::from("LiveActivity"),
+        };
+
+        assert_eq!(
+            json!({"message-storage-policy": 1, "push-type": "LiveActivity"}),
+            serde_json::to_value(&body).unwrap()
+        );
+
+        let body: ChannelBody =
+            serde_json::from_str("{\"message-storage-policy\":0,\"push-type\":\"LiveActivity\"}").unwrap();
+
+        assert_eq!(MessageStoragePolicy::NoStorage, body.message_storage_policy);
+    }
+}
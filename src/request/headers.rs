@@ -0,0 +1,140 @@
+/// The APNs request headers
+use crate::request::notification::NotificationOptions;
+use crate::request::payload::{InterruptionLevel, PayloadLike};
+use std::borrow::Cow;
+
+/// The `apns-*` headers sent along with a notification payload.
+///
+/// Created from the [`NotificationOptions`] of a payload, or returned by
+/// [`NotificationBuilder::build_request`](crate::NotificationBuilder::build_request)
+/// for builders that need headers of their own. Send them with
+/// [`Client::send_with_headers`](crate::Client::send_with_headers).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestHeaders {
+    headers: Vec<(Cow<'static, str>, String)>,
+}
+
+impl RequestHeaders {
+    /// The headers for a payload sent with the given options. Without an
+    /// `apns_priority`, the priority follows the interruption level of the
+    /// payload.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::headers::RequestHeaders;
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::{NotificationOptions, PushType};
+    /// # fn main() {
+    /// let options = NotificationOptions {
+    ///     apns_push_type: Some(PushType::Background),
+    ///     ..Default::default()
+    /// };
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .content_available()
+    ///     .build("token", options.clone());
+    ///
+    /// let headers = RequestHeaders::new(&options, &payload);
+    ///
+    /// assert_eq!(Some("background"), headers.get("apns-push-type"));
+    /// # }
+    /// ```
+    pub fn new<T: PayloadLike>(options: &NotificationOptions<'_>, payload: &T) -> Self {
+        let mut headers = Self::default();
+
+        let apns_priority = options.apns_priority.clone().or_else(|| {
+            payload
+                .get_interruption_level()
+                .and_then(InterruptionLevel::default_priority)
+        });
+
+        if let Some(apns_priority) = apns_priority {
+            headers.insert("apns-priority", apns_priority.to_string());
+        }
+        if let Some(apns_id) = options.apns_id {
+            headers.insert("apns-id", apns_id);
+        }
+        if let Some(apns_push_type) = options.apns_push_type {
+            headers.insert("apns-push-type", apns_push_type.to_string());
+        }
+        if let Some(apns_expiration) = options.apns_expiration {
+            headers.insert("apns-expiration", apns_expiration.to_string());
+        }
+        if let Some(ref apns_collapse_id) = options.apns_collapse_id {
+            headers.insert("apns-collapse-id", apns_collapse_id.value);
+        }
+        if let Some(apns_topic) = options.apns_topic {
+            headers.insert("apns-topic", apns_topic);
+        }
+
+        headers
+    }
+
+    /// Sets a header, replacing any previous value.
+    pub fn insert(&mut self, name: impl Into<Cow<'static, str>>, value: impl Into<String>) -> &mut Self {
+        let name = name.into();
+        let value = value.into();
+
+        match self.headers.iter_mut().find(|(n, _)| n.eq_ignore_ascii_case(&name)) {
+            Some((_, v)) => *v = value,
+            None => self.headers.push((name, value)),
+        }
+
+        self
+    }
+
+    /// The value of a header.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// All headers in the order they were set.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers.iter().map(|(n, v)| (n.as_ref(), v.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::notification::{DefaultNotificationBuilder, NotificationBuilder, Priority};
+
+    #[test]
+    fn test_headers_from_options() {
+        let options = NotificationOptions {
+            apns_id: Some("an-id"),
+            apns_priority: Some(Priority::Normal),
+            apns_topic: Some("com.example.app"),
+            ..Default::default()
+        };
+        let payload = DefaultNotificationBuilder::new()
+            .time_sensitive_interruption_level()
+            .build("token", Default::default());
+
+        let headers = RequestHeaders::new(&options, &payload);
+
+        assert_eq!(
+            vec![
+                ("apns-priority", "5"),
+                ("apns-id", "an-id"),
+                ("apns-topic", "com.example.app"),
+            ],
+            headers.iter().collect::<Vec<_>>()
+        );
+
+        let headers = RequestHeaders::new(&NotificationOptions::default(), &payload);
+
+        assert_eq!(Some("10"), headers.get("apns-priority"));
+    }
+
+    #[test]
+    fn test_insert_replaces() {
+        let mut headers = RequestHeaders::default();
+        headers.insert("apns-push-type", "alert");
+        headers.insert("APNs-Push-Type", "liveactivity");
+
+        assert_eq!(Some("liveactivity"), headers.get("apns-push-type"));
+        assert_eq!(1, headers.iter().count());
+    }
+}
@@ -1,16 +1,54 @@
 /// The `aps` notification content builders
 mod default;
 mod options;
+mod voip;
 mod web;
 
 pub use self::default::{DefaultAlert, DefaultNotificationBuilder, DefaultSound};
 pub use self::options::{CollapseId, NotificationOptions, Priority, PushType};
+pub(crate) use self::options::{validate_collapse_id, validate_topic};
+pub use self::voip::VoipNotificationBuilder;
 pub use self::web::{WebNotificationBuilder, WebPushAlert};
 
+use crate::error::Error;
+use crate::request::headers::RequestHeaders;
 use crate::request::payload::Payload;
+use crate::request::target::Target;
 
 pub trait NotificationBuilder<'a> {
     /// Generates the request payload to be send with the `Client`.
-    fn build(self, device_token: impl Into<std::borrow::Cow<'a, str>>, options: NotificationOptions<'a>)
-    -> Payload<'a>;
+    fn build(self, target: impl Into<Target<'a>>, options: NotificationOptions<'a>) -> Payload<'a>;
+
+    /// Generates the request payload like `build`, but fails if the payload
+    /// would be rejected by APNs or not behave as intended.
+    ///
+    /// See [`Payload::validate`] for the checks done.
+    fn try_build(self, target: impl Into<Target<'a>>, options: NotificationOptions<'a>) -> Result<Payload<'a>, Error>
+    where
+        Self: Sized,
+    {
+        let payload = self.build(target, options);
+        payload.validate()?;
+
+        Ok(payload)
+    }
+
+    /// Generates the request payload together with its request headers. The
+    /// headers are derived from the options, builders needing headers of
+    /// their own can override this.
+    ///
+    /// Send both with [`Client::send_with_headers`](crate::Client::send_with_headers).
+    fn build_request(
+        self,
+        target: impl Into<Target<'a>>,
+        options: NotificationOptions<'a>,
+    ) -> (Payload<'a>, RequestHeaders)
+    where
+        Self: Sized,
+    {
+        let payload = self.build(target, options);
+        let headers = RequestHeaders::new(&payload.options, &payload);
+
+        (payload, headers)
+    }
 }
@@ -1,6 +1,8 @@
 use crate::InterruptionLevel;
-use crate::request::notification::{NotificationBuilder, NotificationOptions};
+use crate::error::Error;
+use crate::request::notification::{NotificationBuilder, NotificationOptions, Priority};
 use crate::request::payload::{APS, APSAlert, APSSound, Payload};
+use crate::request::target::Target;
 
 use std::{borrow::Cow, collections::BTreeMap};
 
@@ -40,7 +42,7 @@ mod bool_as_u8 {
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
 #[serde(rename_all = "kebab-case")]
 pub struct DefaultSound<'a> {
-    #[serde(skip_serializing_if = "std::ops::Not::not", with = "bool_as_u8")]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not", with = "bool_as_u8")]
     critical: bool,
 
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -50,6 +52,43 @@ pub struct DefaultSound<'a> {
     volume: Option<f64>,
 }
 
+/// Audio file extensions supported for notification sounds
+const SOUND_EXTENSIONS: [&str; 4] = ["aif", "aiff", "caf", "wav"];
+
+/// Whether the sound is the system sound or a file name with an extension
+/// supported by APNs. A sound file that's not in the app bundle is replaced
+/// with the system sound without any error.
+fn is_known_sound(name: &str) -> bool {
+    name == "default"
+        || name
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| SOUND_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+}
+
+/// Logs a warning if the sound name is not a file with an extension supported
+/// by APNs.
+pub(super) fn warn_on_unknown_sound(name: &str) {
+    if !is_known_sound(name) {
+        #[cfg(feature = "tracing")]
+        {
+            tracing::warn!(
+                "Sound {:?} has no .aif, .aiff, .caf or .wav extension, the system sound will be played instead",
+                name,
+            );
+        }
+    }
+}
+
+/// Whether exactly one of the interruption level and the sound is critical.
+/// An unset interruption level never mismatches.
+fn is_critical_mismatch(interruption_level: Option<&InterruptionLevel>, critical_sound: bool) -> bool {
+    match interruption_level {
+        Some(InterruptionLevel::Critical) => !critical_sound,
+        Some(_) => critical_sound,
+        None => false,
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct DefaultAlert<'a> {
@@ -85,6 +124,22 @@ pub struct DefaultAlert<'a> {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     loc_args: Option<Vec<Cow<'a, str>>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary_arg: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary_arg_count: Option<u32>,
+}
+
+impl<'a> DefaultAlert<'a> {
+    pub(crate) fn subtitle_mut(&mut self) -> &mut Option<Cow<'a, str>> {
+        &mut self.subtitle
+    }
+
+    pub(crate) fn body_mut(&mut self) -> &mut Option<Cow<'a, str>> {
+        &mut self.body
+    }
 }
 
 /// A builder to create an APNs payload.
@@ -119,14 +174,19 @@ pub struct DefaultAlert<'a> {
 #[derive(Debug, Clone, Default)]
 pub struct DefaultNotificationBuilder<'a> {
     alert: DefaultAlert<'a>,
+    alert_raw: Option<serde_json::Value>,
     badge: Option<u32>,
     sound: DefaultSound<'a>,
+    force_sound_object: bool,
     thread_id: Option<Cow<'a, str>>,
     category: Option<Cow<'a, str>>,
+    target_content_id: Option<Cow<'a, str>>,
     mutable_content: u8,
     content_available: Option<u8>,
     interruption_level: Option<InterruptionLevel>,
+    relevance_score: Option<f64>,
     timestamp: Option<u64>,
+    stale_date: Option<u64>,
     event: Option<Cow<'a, str>>,
     content_state: Option<serde_json::Value>,
     attributes_type: Option<Cow<'a, str>>,
@@ -134,6 +194,7 @@ pub struct DefaultNotificationBuilder<'a> {
     input_push_channel: Option<Cow<'a, str>>,
     input_push_token: Option<u8>,
     dismissal_date: Option<u64>,
+    data: BTreeMap<Cow<'a, str>, serde_json::Value>,
 }
 
 impl<'a> DefaultNotificationBuilder<'a> {
@@ -158,6 +219,76 @@ impl<'a> DefaultNotificationBuilder<'a> {
         Self::default()
     }
 
+    /// Creates a builder for a chat message notification, grouped by the
+    /// conversation's `thread_id` and showing the actions of `category`.
+    /// Plays the default sound and sets `mutable-content`, so a notification
+    /// service extension can decrypt the message. Fill in the title and body.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::chat_message("chat-42", "MESSAGE")
+    ///     .title("Alice")
+    ///     .body("New message")
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"Alice\",\"body\":\"New message\"},\"sound\":\"default\",\"thread-id\":\"chat-42\",\"category\":\"MESSAGE\",\"mutable-content\":1}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn chat_message(thread_id: impl Into<Cow<'a, str>>, category: impl Into<Cow<'a, str>>) -> Self {
+        Self::new()
+            .sound("default")
+            .thread_id(thread_id)
+            .category(category)
+            .mutable_content()
+    }
+
+    /// Creates a builder for starting a Live Activity, setting the `start`
+    /// event with the attributes and initial content state the activity is
+    /// created with. Send it with [`PushType::LiveActivity`] and fill in the
+    /// alert shown when the activity starts.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # use apns_h2::PushType;
+    /// # use serde_json::json;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::start_live_activity(
+    ///     "DeliveryAttributes",
+    ///     &json!({"orderId": "42"}),
+    ///     &json!({"status": "preparing"}),
+    ///     1700000000,
+    /// )
+    /// .title("Order 42")
+    /// .body("Your order is being prepared")
+    /// .build("token", Default::default());
+    ///
+    /// assert!(payload.validate_for(PushType::LiveActivity).is_ok());
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"Order 42\",\"body\":\"Your order is being prepared\"},\"mutable-content\":0,\"timestamp\":1700000000,\"event\":\"start\",\"content-state\":{\"status\":\"preparing\"},\"attributes-type\":\"DeliveryAttributes\",\"attributes\":{\"orderId\":\"42\"}}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn start_live_activity(
+        attributes_type: impl Into<Cow<'a, str>>,
+        attributes: &serde_json::Value,
+        content_state: &serde_json::Value,
+        timestamp: u64,
+    ) -> Self {
+        Self::new()
+            .event("start")
+            .attributes_type(attributes_type)
+            .attributes(attributes)
+            .content_state(content_state)
+            .timestamp(timestamp)
+    }
+
     /// Set the title of the notification.
     /// Apple Watch displays this string in the short look notification interface.
     /// Specify a string that's quickly understood by the user.
@@ -226,6 +357,35 @@ impl<'a> DefaultNotificationBuilder<'a> {
         self.critical(critical, volume)
     }
 
+    /// Mark the notification as critical with a volume in percent, from 0 to
+    /// 100. Fails for a larger value.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() -> Result<(), apns_h2::Error> {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .critical_volume_percent(50)?
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"sound\":{\"critical\":1,\"volume\":0.5},\"mutable-content\":0}}",
+    ///     &payload.to_json_string()?
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn critical_volume_percent(self, percent: u8) -> Result<Self, Error> {
+        if percent > 100 {
+            return Err(Error::InvalidOptions(format!(
+                "The volume of {}% is not between 0 and 100%.",
+                percent
+            )));
+        }
+
+        Ok(self.critical(true, Some(f64::from(percent) / 100.0)))
+    }
+
     /// Used to set the subtitle which should provide additional information that explains the purpose of the notification.
     ///
     /// ```rust
@@ -276,6 +436,37 @@ impl<'a> DefaultNotificationBuilder<'a> {
         self
     }
 
+    /// Sets the content of the alert message, replacing every `{}` in the
+    /// `template` with the next of the `args`. Placeholders without an
+    /// argument are kept as they are and additional arguments are ignored.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let mut builder = DefaultNotificationBuilder::new()
+    ///     .body_fmt("You have {} messages", &["3"]);
+    /// let payload = builder.build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"body\":\"You have 3 messages\"},\"mutable-content\":0}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn body_fmt(self, template: &str, args: &[&str]) -> Self {
+        let mut args = args.iter();
+        let mut parts = template.split("{}");
+        let mut body = String::from(parts.next().unwrap_or_default());
+
+        for part in parts {
+            body.push_str(args.next().copied().unwrap_or("{}"));
+            body.push_str(part);
+        }
+
+        self.body(body)
+    }
+
     #[deprecated(since = "0.11.0", note = "Use the idiomatic `body` instead of the legacy `set_*` fn")]
     pub fn set_body(self, body: impl Into<Cow<'a, str>>) -> Self {
         self.body(body)
@@ -328,7 +519,10 @@ impl<'a> DefaultNotificationBuilder<'a> {
     /// # }
     /// ```
     pub fn sound(mut self, sound: impl Into<Cow<'a, str>>) -> Self {
-        self.sound.name = Some(sound.into());
+        let sound = sound.into();
+        warn_on_unknown_sound(&sound);
+
+        self.sound.name = Some(sound);
         self
     }
 
@@ -340,7 +534,33 @@ impl<'a> DefaultNotificationBuilder<'a> {
         self.sound(sound)
     }
 
+    /// Always send a named sound as an object with a `name`, like a critical
+    /// sound, instead of just the name.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let mut builder = DefaultNotificationBuilder::new()
+    ///     .title("a title")
+    ///     .sound("ping.caf")
+    ///     .force_sound_object();
+    /// let payload = builder.build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"a title\"},\"sound\":{\"name\":\"ping.caf\"},\"mutable-content\":0}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn force_sound_object(mut self) -> Self {
+        self.force_sound_object = true;
+        self
+    }
+
     /// An application-specific name that allows notifications to be grouped together.
+    /// Only visible notifications are grouped, so building a notification
+    /// without an alert, sound or badge logs a warning.
     ///
     /// ```rust
     /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
@@ -393,6 +613,76 @@ impl<'a> DefaultNotificationBuilder<'a> {
         self.category(category)
     }
 
+    /// The identifier of the window brought forward when the user opens the
+    /// notification.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let mut builder = DefaultNotificationBuilder::new()
+    ///     .title("a title")
+    ///     .target_content_id("conversation-42");
+    /// let payload = builder.build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"a title\"},\"mutable-content\":0,\"target-content-id\":\"conversation-42\"}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn target_content_id(mut self, target_content_id: impl Into<Cow<'a, str>>) -> Self {
+        self.target_content_id = Some(target_content_id.into());
+        self
+    }
+
+    /// The string the system appends to the summary of a notification group,
+    /// e.g. the name of the sender in "3 more messages from Alice".
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let mut builder = DefaultNotificationBuilder::new()
+    ///     .title("a title")
+    ///     .summary_arg("Alice");
+    /// let payload = builder.build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"a title\",\"summary-arg\":\"Alice\"},\"mutable-content\":0}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn summary_arg(mut self, summary_arg: impl Into<Cow<'a, str>>) -> Self {
+        self.alert.summary_arg = Some(summary_arg.into());
+        self
+    }
+
+    /// The number of items the notification adds to the summary of its group,
+    /// e.g. the number of messages bundled into a single notification.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let mut builder = DefaultNotificationBuilder::new()
+    ///     .title("a title")
+    ///     .summary_arg("Alice")
+    ///     .summary_arg_count(3);
+    /// let payload = builder.build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"a title\",\"summary-arg\":\"Alice\",\"summary-arg-count\":3},\"mutable-content\":0}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn summary_arg_count(mut self, summary_arg_count: u32) -> Self {
+        self.alert.summary_arg_count = Some(summary_arg_count);
+        self
+    }
+
     /// The subtitle localization key for the notification title.
     ///
     /// ```rust
@@ -608,7 +898,45 @@ impl<'a> DefaultNotificationBuilder<'a> {
         self.loc_args(key)
     }
 
-    /// Image to display in the rich notification.
+    /// Removes the arguments for the content localization, omitting the
+    /// `loc-args` key. Setting an empty list with `loc_args` instead sends
+    /// `"loc-args":[]`.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let no_args: &[&str] = &[];
+    ///
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .title("a title")
+    ///     .loc_args(no_args)
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"a title\",\"loc-args\":[]},\"mutable-content\":0}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    ///
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .title("a title")
+    ///     .loc_args(no_args)
+    ///     .clear_loc_args()
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"a title\"},\"mutable-content\":0}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn clear_loc_args(mut self) -> Self {
+        self.alert.loc_args = None;
+        self
+    }
+
+    /// Image to display in the rich notification. Can be set without any
+    /// other alert field, producing an alert with only the `launch-image`.
     ///
     /// ```rust
     /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
@@ -660,6 +988,31 @@ impl<'a> DefaultNotificationBuilder<'a> {
         self
     }
 
+    /// Set whether the client may modify push content before displaying,
+    /// e.g. depending on a runtime condition.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let has_attachment = false;
+    /// let mut builder = DefaultNotificationBuilder::new()
+    ///     .title("a title")
+    ///     .mutable_content()
+    ///     .mutable_content_value(has_attachment);
+    /// let payload = builder.build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"a title\"},\"mutable-content\":0}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn mutable_content_value(mut self, on: bool) -> Self {
+        self.mutable_content = u8::from(on);
+        self
+    }
+
     #[deprecated(
         since = "0.11.0",
         note = "Use the idiomatic `mutable_content` instead of the legacy `set_*` fn"
@@ -771,6 +1124,10 @@ impl<'a> DefaultNotificationBuilder<'a> {
     /// immediately, lights up the screen, can play a sound, and breaks through system
     /// notification controls.
     ///
+    /// Requires the Time Sensitive Notifications entitlement, without it the
+    /// notification is delivered as active. With the `tracing` feature, the
+    /// first time-sensitive notification built logs a reminder.
+    ///
     /// ```rust
     /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
     /// # use apns_h2::request::payload::PayloadLike;
@@ -813,6 +1170,57 @@ impl<'a> DefaultNotificationBuilder<'a> {
         self
     }
 
+    /// How relevant the notification is compared to the other notifications
+    /// of the app, deciding which one to feature in a notification summary.
+    /// The score is clamped to the range from 0 to 1.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let mut builder = DefaultNotificationBuilder::new()
+    ///     .title("a title")
+    ///     .relevance_score(0.8);
+    /// let payload = builder.build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"a title\"},\"mutable-content\":0,\"relevance-score\":0.8}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn relevance_score(mut self, score: f64) -> Self {
+        self.relevance_score = Some(if score.is_nan() { 0.0 } else { score.clamp(0.0, 1.0) });
+        self
+    }
+
+    /// Prepare the notification to be read aloud by Siri, e.g. through AirPods
+    /// or CarPlay, by marking it time sensitive. Time sensitive notifications
+    /// are announced even if the user only allows announcing those.
+    ///
+    /// The notification needs an alert and the app must register the
+    /// [`category`](Self::category) with the `allowAnnouncement` option.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let mut builder = DefaultNotificationBuilder::new()
+    ///     .title("a title")
+    ///     .category("announced")
+    ///     .announce();
+    /// let payload = builder.build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"a title\"},\"category\":\"announced\",\"mutable-content\":0,\"interruption-level\":\"time-sensitive\"}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn announce(self) -> Self {
+        self.time_sensitive_interruption_level()
+    }
+
     /// Set the timestamp for a Live Activity update
     ///
     /// ```rust
@@ -834,8 +1242,40 @@ impl<'a> DefaultNotificationBuilder<'a> {
         self
     }
 
+    /// Set the date when the content of a Live Activity becomes outdated, in
+    /// seconds since the UNIX epoch. Unlike the
+    /// [`dismissal_date`](Self::dismissal_date), the Live Activity stays
+    /// visible and is marked as stale.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .timestamp(1234)
+    ///     .stale_date(1672531200)
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"mutable-content\":0,\"timestamp\":1234,\"stale-date\":1672531200}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn stale_date(mut self, stale_date: u64) -> Self {
+        self.stale_date = Some(stale_date);
+        self
+    }
+
     /// Set the event for a Live Activity. Use "start" to begin a Live Activity.
     ///
+    /// An update may also carry an alert, e.g. set with [`title`](Self::title)
+    /// and [`body`](Self::body), to show a banner along with the update.
+    ///
+    /// An "update" is sent with [`Priority::Normal`] unless the options set
+    /// an `apns_priority`, as the high priority is budgeted for Live
+    /// Activities.
+    ///
     /// ```rust
     /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
     /// # use apns_h2::request::payload::PayloadLike;
@@ -855,6 +1295,74 @@ impl<'a> DefaultNotificationBuilder<'a> {
         self
     }
 
+    /// Start a Live Activity, the same as [`event("start")`](Self::event).
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .start_activity()
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"mutable-content\":0,\"event\":\"start\"}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn start_activity(self) -> Self {
+        self.event("start")
+    }
+
+    /// Update a Live Activity, the same as [`event("update")`](Self::event).
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .update_activity()
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"mutable-content\":0,\"event\":\"update\"}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn update_activity(self) -> Self {
+        self.event("update")
+    }
+
+    /// End a Live Activity, removing it from the Lock Screen at the
+    /// `dismissal_date` in Unix epoch time if given, see
+    /// [`dismissal_date`](Self::dismissal_date). Without a date, the system
+    /// decides when to remove the ended activity.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .end_activity(Some(1672531200))
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"mutable-content\":0,\"dismissal-date\":1672531200,\"event\":\"end\"}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn end_activity(self, dismissal_date: Option<u64>) -> Self {
+        let builder = self.event("end");
+
+        match dismissal_date {
+            Some(dismissal_date) => builder.dismissal_date(dismissal_date),
+            None => builder,
+        }
+    }
+
     /// Set the content state for a Live Activity with dynamic data
     ///
     /// ```rust
@@ -878,6 +1386,33 @@ impl<'a> DefaultNotificationBuilder<'a> {
         self
     }
 
+    /// Set the content state for a Live Activity from any serializable type,
+    /// e.g. the struct shared with the app. Fails if it can't be serialized.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # use serde::Serialize;
+    /// # fn main() -> Result<(), apns_h2::Error> {
+    /// #[derive(Serialize)]
+    /// #[serde(rename_all = "camelCase")]
+    /// struct ContentState {
+    ///     current_health_level: u8,
+    /// }
+    ///
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .try_content_state(&ContentState { current_health_level: 100 })?
+    ///     .build("token", Default::default());
+    ///
+    /// assert!(payload.to_json_string()?.contains("\"content-state\":{\"currentHealthLevel\":100}"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_content_state<T: serde::Serialize>(mut self, content_state: &T) -> Result<Self, Error> {
+        self.content_state = Some(serde_json::to_value(content_state)?);
+        Ok(self)
+    }
+
     /// Set the attributes type for a Live Activity
     ///
     /// ```rust
@@ -922,6 +1457,33 @@ impl<'a> DefaultNotificationBuilder<'a> {
         self
     }
 
+    /// Set the attributes for a Live Activity from any serializable type.
+    /// Fails if they can't be serialized.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # use serde::Serialize;
+    /// # fn main() -> Result<(), apns_h2::Error> {
+    /// #[derive(Serialize)]
+    /// #[serde(rename_all = "camelCase")]
+    /// struct Attributes {
+    ///     order_id: u32,
+    /// }
+    ///
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .try_attributes(&Attributes { order_id: 42 })?
+    ///     .build("token", Default::default());
+    ///
+    /// assert!(payload.to_json_string()?.contains("\"attributes\":{\"orderId\":42}"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_attributes<T: serde::Serialize>(mut self, attributes: &T) -> Result<Self, Error> {
+        self.attributes = Some(serde_json::to_value(attributes)?);
+        Ok(self)
+    }
+
     /// Set the input push channel ID for iOS 18+ channel-based Live Activity updates
     ///
     /// ```rust
@@ -986,23 +1548,109 @@ impl<'a> DefaultNotificationBuilder<'a> {
         self.dismissal_date = Some(dismissal_date);
         self
     }
+
+    /// Set the alert as raw JSON, for alert structures the builder doesn't
+    /// model. Replaces any alert fields set with the other methods.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # use serde_json::json;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .title("ignored")
+    ///     .alert_raw(json!({"title": "a title", "new-hint": "value"}))
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"new-hint\":\"value\",\"title\":\"a title\"},\"mutable-content\":0}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn alert_raw(mut self, alert: serde_json::Value) -> Self {
+        self.alert_raw = Some(alert);
+        self
+    }
+
+    /// Add custom data under several root keys at once. Like
+    /// [`Payload::add_custom_data`], a root key given again replaces the
+    /// previous data.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # use serde_json::json;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .content_available()
+    ///     .custom_data_many([("a", json!(1)), ("b", json!("two")), ("c", json!({"three": 3}))])
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"content-available\":1,\"mutable-content\":0},\"a\":1,\"b\":\"two\",\"c\":{\"three\":3}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn custom_data_many(mut self, entries: impl IntoIterator<Item = (&'a str, serde_json::Value)>) -> Self {
+        self.data
+            .extend(entries.into_iter().map(|(root_key, data)| (root_key.into(), data)));
+        self
+    }
 }
 
 impl<'a> NotificationBuilder<'a> for DefaultNotificationBuilder<'a> {
-    fn build(self, device_token: impl Into<Cow<'a, str>>, options: NotificationOptions<'a>) -> Payload<'a> {
+    fn build(self, target: impl Into<Target<'a>>, options: NotificationOptions<'a>) -> Payload<'a> {
         use std::sync::OnceLock;
 
         static DEFAULT_ALERT: OnceLock<DefaultAlert<'static>> = OnceLock::new();
 
-        Payload {
+        if is_critical_mismatch(self.interruption_level.as_ref(), self.sound.critical) {
+            #[cfg(feature = "tracing")]
+            {
+                tracing::warn!(
+                    "Interruption level {:?} doesn't match the critical sound setting {}",
+                    self.interruption_level,
+                    self.sound.critical,
+                );
+            }
+        }
+
+        if matches!(self.interruption_level, Some(InterruptionLevel::TimeSensitive)) {
+            #[cfg(feature = "tracing")]
+            {
+                static ENTITLEMENT_WARNING: std::sync::Once = std::sync::Once::new();
+
+                ENTITLEMENT_WARNING.call_once(|| {
+                    tracing::warn!(
+                        "Time-sensitive notifications require the Time Sensitive Notifications entitlement, \
+                         without it they are delivered as active"
+                    );
+                });
+            }
+        }
+
+        let options = if self.event.as_deref() == Some("update") {
+            NotificationOptions {
+                apns_priority: options.apns_priority.clone().or(Some(Priority::Normal)),
+                ..options
+            }
+        } else {
+            options
+        };
+
+        let payload = Payload {
             aps: APS {
-                alert: if &self.alert == DEFAULT_ALERT.get_or_init(Default::default) {
+                alert: if let Some(alert) = self.alert_raw {
+                    Some(APSAlert::Raw(alert))
+                } else if &self.alert == DEFAULT_ALERT.get_or_init(Default::default) {
                     None
                 } else {
                     Some(APSAlert::Default(Box::new(self.alert)))
                 },
                 badge: self.badge,
-                sound: if self.sound.critical {
+                sound: if self.sound.critical || (self.force_sound_object && self.sound.name.is_some()) {
                     Some(APSSound::Critical(self.sound))
                 } else {
                     self.sound.name.map(APSSound::Sound)
@@ -1011,27 +1659,44 @@ impl<'a> NotificationBuilder<'a> for DefaultNotificationBuilder<'a> {
                 content_available: self.content_available,
                 category: self.category,
                 mutable_content: Some(self.mutable_content),
+                target_content_id: self.target_content_id,
                 interruption_level: self.interruption_level,
+                relevance_score: self.relevance_score,
                 dismissal_date: self.dismissal_date,
                 url_args: None,
                 timestamp: self.timestamp,
+                stale_date: self.stale_date,
                 event: self.event,
                 content_state: self.content_state,
                 attributes_type: self.attributes_type,
                 attributes: self.attributes,
                 input_push_channel: self.input_push_channel,
                 input_push_token: self.input_push_token,
+                other: serde_json::Map::new(),
             },
-            device_token: device_token.into(),
+            target: target.into(),
             options,
-            data: BTreeMap::new(),
+            data: self.data,
+            streamed_data: BTreeMap::new(),
+            send_check: Default::default(),
+        };
+
+        if payload.has_ungrouped_thread_id() {
+            #[cfg(feature = "tracing")]
+            {
+                tracing::warn!("Thread ID is set on a notification without an alert, sound or badge to group");
+            }
         }
+
+        payload
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::request::notification::PushType;
+    use crate::request::payload::PayloadLike;
     use serde_json::value::to_value;
 
     #[test]
@@ -1077,6 +1742,93 @@ mod tests {
         assert_eq!(expected_payload, to_value(payload).unwrap());
     }
 
+    #[test]
+    fn test_known_sound() {
+        assert!(is_known_sound("default"));
+        assert!(is_known_sound("ping.caf"));
+        assert!(is_known_sound("ping.AIFF"));
+        assert!(is_known_sound("ping.aif"));
+        assert!(is_known_sound("ping.wav"));
+        assert!(!is_known_sound("ping"));
+        assert!(!is_known_sound("ping.mp3"));
+        assert!(!is_known_sound("Default"));
+    }
+
+    #[test]
+    fn test_critical_mismatch() {
+        assert!(!is_critical_mismatch(None, false));
+        assert!(!is_critical_mismatch(None, true));
+        assert!(!is_critical_mismatch(Some(&InterruptionLevel::Critical), true));
+        assert!(!is_critical_mismatch(Some(&InterruptionLevel::Active), false));
+        assert!(is_critical_mismatch(Some(&InterruptionLevel::Critical), false));
+        assert!(is_critical_mismatch(Some(&InterruptionLevel::TimeSensitive), true));
+    }
+
+    #[test]
+    fn test_grouping_fields_combinations() {
+        for (thread_id, target_content_id, summary_arg) in [
+            (None, None, None),
+            (Some("thread"), None, None),
+            (None, Some("window"), None),
+            (None, None, Some("Alice")),
+            (Some("thread"), Some("window"), None),
+            (Some("thread"), None, Some("Alice")),
+            (None, Some("window"), Some("Alice")),
+            (Some("thread"), Some("window"), Some("Alice")),
+        ] {
+            let mut builder = DefaultNotificationBuilder::new().title("the title");
+
+            if let Some(thread_id) = thread_id {
+                builder = builder.thread_id(thread_id);
+            }
+            if let Some(target_content_id) = target_content_id {
+                builder = builder.target_content_id(target_content_id);
+            }
+            if let Some(summary_arg) = summary_arg {
+                builder = builder.summary_arg(summary_arg);
+            }
+
+            let payload = builder.build("device-token", Default::default());
+
+            let mut expected_payload = json!({
+                "aps": {
+                    "alert": {
+                        "title": "the title",
+                    },
+                    "mutable-content": 0
+                }
+            });
+
+            if let Some(thread_id) = thread_id {
+                expected_payload["aps"]["thread-id"] = json!(thread_id);
+            }
+            if let Some(target_content_id) = target_content_id {
+                expected_payload["aps"]["target-content-id"] = json!(target_content_id);
+            }
+            if let Some(summary_arg) = summary_arg {
+                expected_payload["aps"]["alert"]["summary-arg"] = json!(summary_arg);
+            }
+
+            assert_eq!(expected_payload, to_value(payload).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_grouping_fields_json_order() {
+        let payload = DefaultNotificationBuilder::new()
+            .title("the title")
+            .thread_id("thread")
+            .target_content_id("window")
+            .summary_arg("Alice")
+            .summary_arg_count(2)
+            .build("device-token", Default::default());
+
+        assert_eq!(
+            "{\"aps\":{\"alert\":{\"title\":\"the title\",\"summary-arg\":\"Alice\",\"summary-arg-count\":2},\"thread-id\":\"thread\",\"mutable-content\":0,\"target-content-id\":\"window\"}}",
+            payload.to_json_string().unwrap()
+        );
+    }
+
     #[test]
     fn test_loc_args_inputs() {
         let owned_strings: Vec<String> = vec!["hello".to_string(), "world".to_string()];
@@ -1334,4 +2086,285 @@ mod tests {
 
         assert_eq!(expected_payload, to_value(payload).unwrap());
     }
+
+    #[test]
+    fn test_raw_alert_replaces_typed_alert() {
+        let payload = DefaultNotificationBuilder::new()
+            .title("a title")
+            .body("a body")
+            .alert_raw(json!("a plain string alert"))
+            .build("device-token", Default::default());
+
+        let expected_payload = json!({
+            "aps": {
+                "alert": "a plain string alert",
+                "mutable-content": 0,
+            },
+        });
+
+        assert_eq!(expected_payload, to_value(payload).unwrap());
+    }
+
+    #[test]
+    fn test_launch_image_only_alert() {
+        let payload = DefaultNotificationBuilder::new()
+            .launch_image("x")
+            .build("device-token", Default::default());
+
+        let expected_payload = json!({
+            "aps": {
+                "alert": {
+                    "launch-image": "x",
+                },
+                "mutable-content": 0,
+            },
+        });
+
+        assert_eq!(expected_payload, to_value(payload).unwrap());
+    }
+
+    #[test]
+    fn test_live_activity_update_priority() {
+        let update = || DefaultNotificationBuilder::new().event("update").timestamp(1234);
+
+        let payload = update().build("test-token", Default::default());
+
+        assert!(matches!(payload.options.apns_priority, Some(Priority::Normal)));
+
+        let payload = update().build(
+            "test-token",
+            NotificationOptions {
+                apns_priority: Some(Priority::High),
+                ..Default::default()
+            },
+        );
+
+        assert!(matches!(payload.options.apns_priority, Some(Priority::High)));
+
+        let payload = DefaultNotificationBuilder::new()
+            .event("start")
+            .build("test-token", Default::default());
+
+        assert!(payload.options.apns_priority.is_none());
+    }
+
+    #[test]
+    fn test_sound_only_notification() {
+        let payload = DefaultNotificationBuilder::new()
+            .sound("ping")
+            .build("device-token", Default::default());
+
+        assert_eq!(
+            "{\"aps\":{\"sound\":\"ping\",\"mutable-content\":0}}",
+            payload.to_json_string().unwrap()
+        );
+        assert_eq!(PushType::Alert, payload.push_type_hint());
+    }
+
+    #[test]
+    fn test_critical_volume_percent() {
+        let payload = DefaultNotificationBuilder::new()
+            .critical_volume_percent(100)
+            .unwrap()
+            .build("device-token", Default::default());
+
+        assert_eq!(
+            json!({"aps": {"sound": {"critical": 1, "volume": 1.0}, "mutable-content": 0}}),
+            to_value(payload).unwrap()
+        );
+
+        assert!(matches!(
+            DefaultNotificationBuilder::new().critical_volume_percent(101),
+            Err(Error::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn test_ungrouped_thread_id() {
+        let silent = DefaultNotificationBuilder::new()
+            .content_available()
+            .thread_id("my-thread")
+            .build("device-token", Default::default());
+
+        let alert = DefaultNotificationBuilder::new()
+            .title("a title")
+            .thread_id("my-thread")
+            .build("device-token", Default::default());
+
+        assert!(silent.has_ungrouped_thread_id());
+        assert!(!alert.has_ungrouped_thread_id());
+    }
+
+    #[test]
+    fn test_try_content_state_and_attributes() {
+        use std::collections::HashMap;
+
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ContentState {
+            home_score: u8,
+            away_score: u8,
+        }
+
+        #[derive(Serialize)]
+        struct Attributes {
+            team: &'static str,
+        }
+
+        let payload = DefaultNotificationBuilder::new()
+            .event("update")
+            .try_content_state(&ContentState {
+                home_score: 1,
+                away_score: 0,
+            })
+            .unwrap()
+            .try_attributes(&Attributes { team: "home" })
+            .unwrap()
+            .build("device-token", Default::default());
+
+        assert_eq!(Some(json!({"homeScore": 1, "awayScore": 0})), payload.aps.content_state);
+        assert_eq!(Some(json!({"team": "home"})), payload.aps.attributes);
+
+        // JSON objects need string keys
+        let unserializable = HashMap::from([((1, 2), "score")]);
+
+        assert!(matches!(
+            DefaultNotificationBuilder::new().try_content_state(&unserializable),
+            Err(Error::SerializeError(_))
+        ));
+        assert!(matches!(
+            DefaultNotificationBuilder::new().try_attributes(&unserializable),
+            Err(Error::SerializeError(_))
+        ));
+    }
+
+    #[test]
+    fn test_body_fmt() {
+        let body = |builder: DefaultNotificationBuilder<'_>| {
+            let payload = builder.build("device-token", Default::default());
+            payload.aps.alert.and_then(|alert| match alert {
+                APSAlert::Default(alert) => alert.body.map(|body| body.into_owned()),
+                _ => None,
+            })
+        };
+
+        assert_eq!(
+            Some(String::from("Alice sent 2 photos")),
+            body(DefaultNotificationBuilder::new().body_fmt("{} sent {} photos", &["Alice", "2"]))
+        );
+        assert_eq!(
+            Some(String::from("Alice sent {} photos")),
+            body(DefaultNotificationBuilder::new().body_fmt("{} sent {} photos", &["Alice"]))
+        );
+        assert_eq!(
+            Some(String::from("No placeholders")),
+            body(DefaultNotificationBuilder::new().body_fmt("No placeholders", &["unused"]))
+        );
+    }
+
+    #[test]
+    fn test_force_sound_object() {
+        let payload = DefaultNotificationBuilder::new()
+            .sound("ping.caf")
+            .critical(true, Some(0.5))
+            .force_sound_object()
+            .build("device-token", Default::default());
+
+        assert_eq!(
+            json!({"aps": {"sound": {"critical": 1, "name": "ping.caf", "volume": 0.5}, "mutable-content": 0}}),
+            serde_json::to_value(payload).unwrap()
+        );
+
+        // Without a name there is no sound to send
+        let payload = DefaultNotificationBuilder::new()
+            .title("a title")
+            .force_sound_object()
+            .build("device-token", Default::default());
+
+        assert_eq!(
+            json!({"aps": {"alert": {"title": "a title"}, "mutable-content": 0}}),
+            serde_json::to_value(payload).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_relevance_score() {
+        let relevance_score = |score| {
+            DefaultNotificationBuilder::new()
+                .title("a title")
+                .relevance_score(score)
+                .build("device-token", Default::default())
+                .aps
+                .relevance_score
+        };
+
+        assert_eq!(Some(0.8), relevance_score(0.8));
+        assert_eq!(Some(1.0), relevance_score(1.5));
+        assert_eq!(Some(0.0), relevance_score(-0.5));
+        assert_eq!(Some(0.0), relevance_score(f64::NAN));
+
+        let payload = DefaultNotificationBuilder::new()
+            .relevance_score(0.8)
+            .build("device-token", Default::default());
+
+        assert!(payload.to_json_string().unwrap().contains("\"relevance-score\":0.8"));
+    }
+
+    #[test]
+    fn test_live_activity_stale_and_dismissal_date() {
+        let payload = DefaultNotificationBuilder::new()
+            .event("update")
+            .content_state(&json!({"score": 1}))
+            .timestamp(1672531000)
+            .stale_date(1672531200)
+            .dismissal_date(1672534800)
+            .build(
+                "device-token",
+                NotificationOptions {
+                    apns_push_type: Some(PushType::LiveActivity),
+                    ..Default::default()
+                },
+            );
+
+        assert_eq!(
+            json!({
+                "aps": {
+                    "mutable-content": 0,
+                    "dismissal-date": 1672534800,
+                    "timestamp": 1672531000,
+                    "stale-date": 1672531200,
+                    "event": "update",
+                    "content-state": {"score": 1},
+                }
+            }),
+            serde_json::to_value(&payload).unwrap()
+        );
+        assert!(payload.validate_for(PushType::LiveActivity).is_ok());
+    }
+
+    #[test]
+    fn test_live_activity_events() {
+        let event = |builder: DefaultNotificationBuilder<'_>| {
+            serde_json::to_value(builder.build("device-token", Default::default())).unwrap()["aps"].clone()
+        };
+
+        assert_eq!(
+            json!("start"),
+            event(DefaultNotificationBuilder::new().start_activity())["event"]
+        );
+        assert_eq!(
+            json!("update"),
+            event(DefaultNotificationBuilder::new().update_activity())["event"]
+        );
+
+        let ended = event(DefaultNotificationBuilder::new().end_activity(None));
+
+        assert_eq!(json!("end"), ended["event"]);
+        assert!(ended.get("dismissal-date").is_none());
+
+        let dismissed = event(DefaultNotificationBuilder::new().end_activity(Some(1672534800)));
+
+        assert_eq!(json!("end"), dismissed["event"]);
+        assert_eq!(json!(1672534800), dismissed["dismissal-date"]);
+    }
 }
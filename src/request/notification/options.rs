@@ -1,5 +1,8 @@
+use crate::clock::Clock;
 use crate::error::Error;
 use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone)]
 pub struct CollapseId<'a> {
@@ -9,17 +12,26 @@ pub struct CollapseId<'a> {
 /// A collapse-id container. Will not allow bigger id's than 64 bytes.
 impl<'a> CollapseId<'a> {
     pub fn new(value: &'a str) -> Result<CollapseId<'a>, Error> {
-        if value.len() > 64 {
-            Err(Error::InvalidOptions(String::from(
-                "The collapse-id is too big. Maximum 64 bytes.",
-            )))
-        } else {
-            Ok(CollapseId { value })
-        }
+        validate_collapse_id(value)?;
+
+        Ok(CollapseId { value })
+    }
+}
+
+/// Checks that the collapse ID fits the 64 bytes APNs accepts, also for IDs
+/// created without [`CollapseId::new`], as APNs answers longer IDs with a
+/// confusing error.
+pub(crate) fn validate_collapse_id(collapse_id: &str) -> Result<(), Error> {
+    if collapse_id.len() > 64 {
+        return Err(Error::InvalidOptions(String::from(
+            "The collapse-id is too big. Maximum 64 bytes.",
+        )));
     }
+
+    Ok(())
 }
 
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 /// The apns-push-type header field has the following valid values.
 /// The descriptions below describe when and how to use these values.
 /// Send an apns-push-type header with each push. Recent and upcoming features
@@ -40,6 +52,9 @@ pub enum PushType {
     /// The push type for notifications that provide information about an incoming
     /// Voice-over-IP (VoIP) call.
     Voip,
+    /// The push type for notifications that contain update information for a
+    /// watchOS app’s complications.
+    Complication,
     /// The push type to signal changes to a File Provider extension.
     FileProvider,
     /// The push type for notifications that tell managed devices to contact the
@@ -52,18 +67,97 @@ pub enum PushType {
     PushToTalk,
 }
 
-impl fmt::Display for PushType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(match self {
+impl PushType {
+    /// The largest payload in bytes APNs accepts for this push type.
+    ///
+    /// ```rust
+    /// # use apns_h2::PushType;
+    /// # fn main() {
+    /// assert_eq!(4096, PushType::Alert.max_payload_bytes());
+    /// assert_eq!(5120, PushType::Voip.max_payload_bytes());
+    /// # }
+    /// ```
+    pub fn max_payload_bytes(&self) -> usize {
+        match self {
+            PushType::Voip => 5120,
+            _ => 4096,
+        }
+    }
+
+    /// The value of the `apns-push-type` header. Parsed back with
+    /// [`str::parse`].
+    ///
+    /// ```rust
+    /// # use apns_h2::PushType;
+    /// # fn main() {
+    /// assert_eq!("liveactivity", PushType::LiveActivity.as_str());
+    /// assert_eq!(PushType::LiveActivity, "liveactivity".parse().unwrap());
+    /// # }
+    /// ```
+    pub fn as_str(&self) -> &'static str {
+        match self {
             PushType::Alert => "alert",
             PushType::Background => "background",
             PushType::Location => "location",
             PushType::Voip => "voip",
+            PushType::Complication => "complication",
             PushType::FileProvider => "fileprovider",
             PushType::Mdm => "mdm",
             PushType::LiveActivity => "liveactivity",
             PushType::PushToTalk => "pushtotalk",
-        })
+        }
+    }
+
+    /// The push type APNs requires for a topic with a push type suffix, like
+    /// `.voip` for VoIP notifications. `None` for the plain bundle ID, which
+    /// is used for several push types.
+    ///
+    /// ```rust
+    /// # use apns_h2::PushType;
+    /// # fn main() {
+    /// assert_eq!(Some(PushType::Voip), PushType::from_topic("com.example.app.voip"));
+    /// assert_eq!(None, PushType::from_topic("com.example.app"));
+    /// # }
+    /// ```
+    pub fn from_topic(topic: &str) -> Option<PushType> {
+        const SUFFIXES: [(&str, PushType); 6] = [
+            (".voip", PushType::Voip),
+            (".voip-ptt", PushType::PushToTalk),
+            (".complication", PushType::Complication),
+            (".pushkit.fileprovider", PushType::FileProvider),
+            (".push-type.liveactivity", PushType::LiveActivity),
+            (".location-query", PushType::Location),
+        ];
+
+        SUFFIXES
+            .into_iter()
+            .find(|(suffix, _)| topic.ends_with(suffix))
+            .map(|(_, push_type)| push_type)
+    }
+}
+
+impl fmt::Display for PushType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PushType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "alert" => Ok(PushType::Alert),
+            "background" => Ok(PushType::Background),
+            "location" => Ok(PushType::Location),
+            "voip" => Ok(PushType::Voip),
+            "complication" => Ok(PushType::Complication),
+            "fileprovider" => Ok(PushType::FileProvider),
+            "mdm" => Ok(PushType::Mdm),
+            "liveactivity" => Ok(PushType::LiveActivity),
+            "pushtotalk" => Ok(PushType::PushToTalk),
+            _ => Err(Error::InvalidOptions(format!("Unknown push type {:?}.", s))),
+        }
     }
 }
 
@@ -79,6 +173,11 @@ pub struct NotificationOptions<'a> {
     ///
     /// Recent and upcoming features may not work if this header is missing.
     /// See the table above to determine if this header is required or optional.
+    ///
+    /// When unset, the client sends the push type guessed from the payload,
+    /// e.g. [`PushType::Background`] for a payload only setting
+    /// `content-available`, see
+    /// [`ClientConfig::strict_push_type_inference`](crate::ClientConfig::strict_push_type_inference).
     pub apns_push_type: Option<PushType>,
 
     /// A UNIX epoch date expressed in seconds (UTC). This header identifies the
@@ -114,6 +213,130 @@ pub struct NotificationOptions<'a> {
     /// user as a single notification. The value of this key must not exceed 64
     /// bytes.
     pub apns_collapse_id: Option<CollapseId<'a>>,
+
+    /// The ID of the key signing the request, for a client created with
+    /// [`Client::with_token_auths`](crate::Client::with_token_auths). If
+    /// `None`, the first key is used. Not sent to APNs.
+    pub signing_key_id: Option<&'a str>,
+}
+
+impl<'a> NotificationOptions<'a> {
+    /// Sets every option that is `None` to the value in `defaults`.
+    ///
+    /// ```rust
+    /// # use apns_h2::{NotificationOptions, Priority};
+    /// # fn main() {
+    /// let defaults = NotificationOptions {
+    ///     apns_topic: Some("com.example.app"),
+    ///     apns_priority: Some(Priority::Normal),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let options = NotificationOptions {
+    ///     apns_topic: Some("com.example.app.voip"),
+    ///     ..Default::default()
+    /// }
+    /// .with_defaults(&defaults);
+    ///
+    /// assert_eq!(Some("com.example.app.voip"), options.apns_topic);
+    /// assert!(matches!(options.apns_priority, Some(Priority::Normal)));
+    /// # }
+    /// ```
+    pub fn with_defaults(self, defaults: &NotificationOptions<'a>) -> NotificationOptions<'a> {
+        NotificationOptions {
+            apns_id: self.apns_id.or(defaults.apns_id),
+            apns_push_type: self.apns_push_type.or(defaults.apns_push_type),
+            apns_expiration: self.apns_expiration.or(defaults.apns_expiration),
+            apns_priority: self.apns_priority.or_else(|| defaults.apns_priority.clone()),
+            apns_topic: self.apns_topic.or(defaults.apns_topic),
+            apns_collapse_id: self.apns_collapse_id.or_else(|| defaults.apns_collapse_id.clone()),
+            signing_key_id: self.signing_key_id.or(defaults.signing_key_id),
+        }
+    }
+
+    /// Sets the `apns_expiration` to `ttl` from the current time of `clock`,
+    /// e.g. the [`ClientConfig::clock`](crate::ClientConfig::clock) of the
+    /// client. Fails with [`Error::InvalidExpiration`] if the time overflows a
+    /// UNIX timestamp.
+    ///
+    /// ```rust
+    /// # use apns_h2::{NotificationOptions, SystemClock};
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<(), apns_h2::Error> {
+    /// let options = NotificationOptions::default().expire_in(Duration::from_secs(60 * 60), &SystemClock)?;
+    ///
+    /// assert!(options.apns_expiration.is_some());
+    /// assert!(NotificationOptions::default().expire_in(Duration::MAX, &SystemClock).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn expire_in(mut self, ttl: Duration, clock: &dyn Clock) -> Result<Self, Error> {
+        let now = clock.now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let Some(expiration) = now.checked_add(ttl) else {
+            return Err(Error::InvalidExpiration(format!(
+                "{} s from now is beyond the largest UNIX timestamp.",
+                ttl.as_secs()
+            )));
+        };
+
+        self.apns_expiration = Some(expiration.as_secs());
+        Ok(self)
+    }
+
+    /// Sets the `apns_expiration` to the UNIX timestamp of `time`, rounded
+    /// down to whole seconds. Fails with [`Error::InvalidExpiration`] if the
+    /// time is before the UNIX epoch.
+    ///
+    /// ```rust
+    /// # use apns_h2::NotificationOptions;
+    /// # use std::time::{Duration, UNIX_EPOCH};
+    /// # fn main() -> Result<(), apns_h2::Error> {
+    /// let options = NotificationOptions::default().expires_at(UNIX_EPOCH + Duration::from_secs(1672531200))?;
+    ///
+    /// assert_eq!(Some(1672531200), options.apns_expiration);
+    /// assert!(NotificationOptions::default().expires_at(UNIX_EPOCH - Duration::from_secs(1)).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn expires_at(mut self, time: SystemTime) -> Result<Self, Error> {
+        let Ok(expiration) = time.duration_since(UNIX_EPOCH) else {
+            return Err(Error::InvalidExpiration(format!(
+                "{:?} is before the UNIX epoch.",
+                time
+            )));
+        };
+
+        self.apns_expiration = Some(expiration.as_secs());
+        Ok(self)
+    }
+}
+
+/// Checks that the topic looks like a reverse-DNS bundle ID, optionally with a
+/// suffix such as `.voip`, so that malformed topics are rejected before
+/// APNs answers with `TopicDisallowed`.
+pub(crate) fn validate_topic(topic: &str) -> Result<(), Error> {
+    if topic.trim() != topic {
+        return Err(Error::InvalidTopic(format!(
+            "The topic {:?} has leading or trailing whitespace.",
+            topic
+        )));
+    }
+
+    let is_label = |label: &str| {
+        !label.is_empty()
+            && label
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    };
+
+    if topic.split('.').count() < 2 || !topic.split('.').all(is_label) {
+        return Err(Error::InvalidTopic(format!(
+            "The topic {:?} is not a reverse-DNS bundle ID.",
+            topic
+        )));
+    }
+
+    Ok(())
 }
 
 /// The importance how fast to bring the notification for the user..
@@ -129,16 +352,45 @@ pub enum Priority {
     /// grouped and delivered in bursts. They are throttled, and in some cases
     /// are not delivered.
     Normal,
+
+    /// Send the push message prioritizing the power considerations of the
+    /// device over all other factors, without waking up the device.
+    Low,
+}
+
+impl Priority {
+    /// The value of the `apns-priority` header.
+    ///
+    /// ```rust
+    /// # use apns_h2::Priority;
+    /// # fn main() {
+    /// assert_eq!(10, Priority::High.as_u8());
+    /// assert!(matches!(Priority::from_u8(5), Some(Priority::Normal)));
+    /// # }
+    /// ```
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            Priority::High => 10,
+            Priority::Normal => 5,
+            Priority::Low => 1,
+        }
+    }
+
+    /// The priority for a value of the `apns-priority` header, or `None` if
+    /// APNs doesn't know the value.
+    pub fn from_u8(priority: u8) -> Option<Self> {
+        match priority {
+            10 => Some(Priority::High),
+            5 => Some(Priority::Normal),
+            1 => Some(Priority::Low),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for Priority {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let priority = match self {
-            Priority::High => "10",
-            Priority::Normal => "5",
-        };
-
-        write!(f, "{}", priority)
+        write!(f, "{}", self.as_u8())
     }
 }
 
@@ -161,4 +413,107 @@ mod tests {
         let collapse_id = CollapseId::new(str::from_utf8(&long_string).unwrap());
         assert!(collapse_id.is_err());
     }
+
+    #[test]
+    fn test_push_type_round_trip() {
+        let push_types = [
+            PushType::Alert,
+            PushType::Background,
+            PushType::Location,
+            PushType::Voip,
+            PushType::Complication,
+            PushType::FileProvider,
+            PushType::Mdm,
+            PushType::LiveActivity,
+            PushType::PushToTalk,
+        ];
+
+        for push_type in push_types {
+            assert_eq!(push_type, push_type.as_str().parse::<PushType>().unwrap());
+        }
+
+        assert!(matches!("Alert".parse::<PushType>(), Err(Error::InvalidOptions(_))));
+    }
+
+    #[test]
+    fn test_priority_round_trip() {
+        for priority in [Priority::High, Priority::Normal, Priority::Low] {
+            assert_eq!(
+                Some(priority.as_u8()),
+                Priority::from_u8(priority.as_u8()).map(|p| p.as_u8())
+            );
+        }
+
+        assert!(Priority::from_u8(7).is_none());
+    }
+
+    #[test]
+    fn test_validate_topic() {
+        assert!(validate_topic("com.example.app").is_ok());
+        assert!(validate_topic("com.example.my-app.voip").is_ok());
+        assert!(validate_topic("com.example.app.push-type.liveactivity").is_ok());
+
+        assert!(matches!(
+            validate_topic(" com.example.app"),
+            Err(Error::InvalidTopic(_))
+        ));
+        assert!(matches!(
+            validate_topic("com.example.app\n"),
+            Err(Error::InvalidTopic(_))
+        ));
+        assert!(matches!(validate_topic("example"), Err(Error::InvalidTopic(_))));
+        assert!(matches!(validate_topic("com..app"), Err(Error::InvalidTopic(_))));
+        assert!(matches!(validate_topic("com.example app"), Err(Error::InvalidTopic(_))));
+    }
+
+    #[test]
+    fn test_expire_in() {
+        use crate::clock::ManualClock;
+
+        let now = 1672531200;
+        let clock = ManualClock::new(now);
+        let expiration = NotificationOptions::default()
+            .expire_in(Duration::from_secs(60), &clock)
+            .unwrap()
+            .apns_expiration;
+
+        assert_eq!(Some(now + 60), expiration);
+
+        let ttl = Duration::from_secs(u64::MAX - now);
+
+        assert_eq!(
+            Some(u64::MAX),
+            NotificationOptions::default()
+                .expire_in(ttl, &clock)
+                .unwrap()
+                .apns_expiration
+        );
+        assert!(matches!(
+            NotificationOptions::default().expire_in(ttl + Duration::from_secs(1), &clock),
+            Err(Error::InvalidExpiration(_))
+        ));
+        assert!(matches!(
+            NotificationOptions::default().expire_in(Duration::MAX, &clock),
+            Err(Error::InvalidExpiration(_))
+        ));
+    }
+
+    #[test]
+    fn test_expires_at() {
+        let expires_at = |time| {
+            NotificationOptions::default()
+                .expires_at(time)
+                .map(|o| o.apns_expiration)
+        };
+
+        assert_eq!(Some(0), expires_at(UNIX_EPOCH).unwrap());
+        assert_eq!(
+            Some(1672531200),
+            expires_at(UNIX_EPOCH + Duration::from_millis(1672531200999)).unwrap()
+        );
+        assert!(matches!(
+            expires_at(UNIX_EPOCH - Duration::from_secs(1)),
+            Err(Error::InvalidExpiration(_))
+        ));
+    }
 }
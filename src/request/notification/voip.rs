@@ -0,0 +1,107 @@
+use crate::error::Error;
+use crate::request::notification::{NotificationBuilder, NotificationOptions, Priority, PushType};
+use crate::request::payload::{APS, Payload};
+use crate::request::target::Target;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+/// A builder to create a PushKit VoIP notification payload.
+///
+/// VoIP notifications carry no alert, the app receives the custom data in its
+/// PushKit handler and reports the incoming call. The push type defaults to
+/// [`PushType::Voip`] and the priority to [`Priority::High`]. The
+/// `apns_topic` must be the bundle ID with the `.voip` suffix.
+///
+/// # Example
+///
+/// ```rust
+/// # use apns_h2::request::notification::{NotificationBuilder, NotificationOptions, VoipNotificationBuilder};
+/// # use apns_h2::request::payload::PayloadLike;
+/// # use serde_json::json;
+/// # fn main() -> Result<(), apns_h2::Error> {
+/// let payload = VoipNotificationBuilder::new()
+///     .custom_data("call", &json!({"caller": "Alice"}))?
+///     .build(
+///         "token",
+///         NotificationOptions {
+///             apns_topic: Some("com.example.app.voip"),
+///             ..Default::default()
+///         },
+///     );
+///
+/// assert_eq!(
+///     "{\"aps\":{},\"call\":{\"caller\":\"Alice\"}}",
+///     &payload.to_json_string()?
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct VoipNotificationBuilder<'a> {
+    data: BTreeMap<Cow<'a, str>, serde_json::Value>,
+}
+
+impl<'a> VoipNotificationBuilder<'a> {
+    /// Creates a new builder without any data.
+    pub fn new() -> VoipNotificationBuilder<'a> {
+        Self::default()
+    }
+
+    /// Adds custom data under `root_key`, handed to the PushKit handler of
+    /// the app.
+    pub fn custom_data(
+        mut self,
+        root_key: impl Into<Cow<'a, str>>,
+        data: &dyn erased_serde::Serialize,
+    ) -> Result<Self, Error> {
+        self.data.insert(root_key.into(), serde_json::to_value(data)?);
+
+        Ok(self)
+    }
+}
+
+impl<'a> NotificationBuilder<'a> for VoipNotificationBuilder<'a> {
+    fn build(self, target: impl Into<Target<'a>>, options: NotificationOptions<'a>) -> Payload<'a> {
+        #[cfg(feature = "tracing")]
+        {
+            if let Some(apns_topic) = options.apns_topic.filter(|topic| !topic.ends_with(".voip")) {
+                tracing::warn!("VoIP notifications need a topic ending in .voip, got {}", apns_topic);
+            }
+        }
+
+        Payload {
+            aps: APS::default(),
+            target: target.into(),
+            options: NotificationOptions {
+                apns_push_type: options.apns_push_type.or(Some(PushType::Voip)),
+                apns_priority: options.apns_priority.clone().or(Some(Priority::High)),
+                ..options
+            },
+            data: self.data,
+            streamed_data: BTreeMap::new(),
+            send_check: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, to_value};
+
+    #[test]
+    fn test_voip_notification() {
+        let payload = VoipNotificationBuilder::new()
+            .custom_data("call", &json!({"caller": "Alice"}))
+            .unwrap()
+            .build("device-token", Default::default());
+
+        assert_eq!(Some(PushType::Voip), payload.options.apns_push_type);
+        assert!(matches!(payload.options.apns_priority, Some(Priority::High)));
+        assert_eq!(
+            json!({"aps": {}, "call": {"caller": "Alice"}}),
+            to_value(&payload).unwrap()
+        );
+        assert!(payload.validate().is_ok());
+    }
+}
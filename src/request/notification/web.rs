@@ -1,5 +1,7 @@
+use crate::error::Error;
 use crate::request::notification::{NotificationBuilder, NotificationOptions};
 use crate::request::payload::{APS, APSAlert, APSSound, Payload};
+use crate::request::target::Target;
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 
@@ -11,6 +13,65 @@ pub struct WebPushAlert<'a> {
     pub action: &'a str,
 }
 
+impl<'a> WebPushAlert<'a> {
+    /// Parses an alert from its title, body and action separated by
+    /// `delimiter`. Every part must be non-empty.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::WebPushAlert;
+    /// # fn main() -> Result<(), apns_h2::Error> {
+    /// let alert = WebPushAlert::parse("Hello;World;View", ';')?;
+    ///
+    /// assert_eq!(("Hello", "World", "View"), (alert.title, alert.body, alert.action));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse(s: &'a str, delimiter: char) -> Result<Self, Error> {
+        let parts: Vec<&str> = s.split(delimiter).collect();
+
+        let [title, body, action] = parts[..] else {
+            return Err(Error::InvalidWebPushAlert(format!(
+                "expected title, body and action separated by '{delimiter}', got {} parts",
+                parts.len()
+            )));
+        };
+
+        if let Some(name) = [("title", title), ("body", body), ("action", action)]
+            .iter()
+            .find(|(_, part)| part.is_empty())
+            .map(|(name, _)| name)
+        {
+            return Err(Error::InvalidWebPushAlert(format!("the {name} is empty")));
+        }
+
+        Ok(WebPushAlert { title, body, action })
+    }
+}
+
+/// Parses an alert from `title|body|action`, see [`WebPushAlert::parse`].
+///
+/// ```rust
+/// # use apns_h2::request::notification::{NotificationBuilder, WebNotificationBuilder, WebPushAlert};
+/// # use apns_h2::request::payload::PayloadLike;
+/// # fn main() -> Result<(), apns_h2::Error> {
+/// let alert = WebPushAlert::try_from("Hello|World|View")?;
+/// let payload = WebNotificationBuilder::new(alert, &["arg1"]).build("token", Default::default());
+///
+/// assert_eq!(
+///     "{\"aps\":{\"alert\":{\"title\":\"Hello\",\"body\":\"World\",\"action\":\"View\"},\"url-args\":[\"arg1\"]}}",
+///     &payload.to_json_string()?
+/// );
+/// # Ok(())
+/// # }
+/// ```
+impl<'a> TryFrom<&'a str> for WebPushAlert<'a> {
+    type Error = Error;
+
+    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
+        Self::parse(s, '|')
+    }
+}
+
 /// A builder to create a simple APNs notification payload.
 ///
 /// # Example
@@ -79,7 +140,10 @@ impl<'a> WebNotificationBuilder<'a> {
     /// # }
     /// ```
     pub fn sound(&mut self, sound: impl Into<Cow<'a, str>>) -> &mut Self {
-        self.sound = Some(sound.into());
+        let sound = sound.into();
+        super::default::warn_on_unknown_sound(&sound);
+
+        self.sound = Some(sound);
         self
     }
 
@@ -273,7 +337,7 @@ impl<'a> WebNotificationBuilder<'a> {
 }
 
 impl<'a> NotificationBuilder<'a> for WebNotificationBuilder<'a> {
-    fn build(self, device_token: impl Into<Cow<'a, str>>, options: NotificationOptions<'a>) -> Payload<'a> {
+    fn build(self, target: impl Into<Target<'a>>, options: NotificationOptions<'a>) -> Payload<'a> {
         Payload {
             aps: APS {
                 alert: Some(APSAlert::WebPush(self.alert)),
@@ -283,20 +347,26 @@ impl<'a> NotificationBuilder<'a> for WebNotificationBuilder<'a> {
                 content_available: None,
                 category: None,
                 mutable_content: None,
+                target_content_id: None,
                 interruption_level: self.interruption_level,
+                relevance_score: None,
                 dismissal_date: self.dismissal_date,
                 url_args: Some(self.url_args),
                 timestamp: None,
+                stale_date: None,
                 event: None,
                 content_state: None,
                 attributes_type: None,
                 attributes: None,
                 input_push_channel: None,
                 input_push_token: None,
+                other: serde_json::Map::new(),
             },
-            device_token: device_token.into(),
+            target: target.into(),
             options,
             data: BTreeMap::new(),
+            streamed_data: BTreeMap::new(),
+            send_check: Default::default(),
         }
     }
 }
@@ -335,6 +405,20 @@ mod tests {
         assert_eq!(expected_payload, serde_json::from_str::<Value>(&payload).unwrap());
     }
 
+    #[test]
+    fn test_parse_webpush_alert() {
+        let alert = WebPushAlert::try_from("Hello|World|View").unwrap();
+
+        assert_eq!(("Hello", "World", "View"), (alert.title, alert.body, alert.action));
+
+        for invalid in ["Hello|World", "Hello|World|View|More", "Hello||View", ""] {
+            assert!(
+                matches!(WebPushAlert::try_from(invalid), Err(Error::InvalidWebPushAlert(_))),
+                "{invalid}"
+            );
+        }
+    }
+
     #[test]
     fn test_webpush_notification_with_dismissal_date() {
         let mut builder = WebNotificationBuilder::new(
@@ -1,26 +1,83 @@
 /// Payload with `aps` and custom data
 use crate::error::Error;
-use crate::request::notification::{DefaultAlert, DefaultSound, NotificationOptions, WebPushAlert};
+use crate::request::notification::{DefaultAlert, DefaultSound, NotificationOptions, Priority, PushType, WebPushAlert};
+use crate::request::target::Target;
 use erased_serde::Serialize;
 use serde_json::{self, Value};
 use std::borrow::Cow;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
 
+/// The largest serialized `attributes` of a Live Activity start accepted by
+/// [`Payload::validate`]. Leaves room for the content state and alert within
+/// the payload limit of 4 KB.
+pub const MAX_ATTRIBUTES_BYTES: usize = 2048;
+
 /// The data and options for a push notification.
 #[derive(Debug, Clone, Serialize)]
 pub struct Payload<'a> {
     /// Send options
     #[serde(skip)]
     pub options: NotificationOptions<'a>,
-    /// The token for the receiving device
+    /// The receiving device or broadcast channel
     #[serde(skip)]
-    pub device_token: Cow<'a, str>,
+    pub target: Target<'a>,
     /// The pre-defined notification payload
     pub aps: APS<'a>,
     /// Application specific payload
     #[serde(flatten)]
     pub data: BTreeMap<Cow<'a, str>, Value>,
+    /// Application specific payload serialized only with the payload, see
+    /// [`Payload::add_custom_data_streaming`]
+    #[serde(flatten)]
+    pub(crate) streamed_data: BTreeMap<Cow<'a, str>, StreamedData<'a>>,
+    /// Warns about the payload being dropped without being sent or serialized
+    #[serde(skip)]
+    pub(crate) send_check: SendCheck,
+}
+
+/// Custom data borrowed by a payload and serialized along with it.
+#[derive(Clone, Copy, Serialize)]
+#[serde(transparent)]
+pub(crate) struct StreamedData<'a>(&'a (dyn Serialize + Sync));
+
+impl Debug for StreamedData<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("StreamedData")
+    }
+}
+
+/// Logs a warning when the payload holding it is dropped without being
+/// sent, in debug builds with the `tracing` feature. Payloads exported with
+/// [`Payload::serialize_into`] or [`payloads_to_ndjson`] count as sent, while
+/// measuring, hashing or previewing a payload doesn't.
+#[derive(Debug, Default)]
+pub(crate) struct SendCheck {
+    #[cfg(all(debug_assertions, feature = "tracing"))]
+    sent: std::sync::atomic::AtomicBool,
+}
+
+impl SendCheck {
+    pub(crate) fn mark_sent(&self) {
+        #[cfg(all(debug_assertions, feature = "tracing"))]
+        self.sent.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+impl Clone for SendCheck {
+    /// A clone is a payload of its own, which has not been sent yet.
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(all(debug_assertions, feature = "tracing"))]
+impl Drop for SendCheck {
+    fn drop(&mut self) {
+        if !*self.sent.get_mut() {
+            tracing::warn!("A notification payload was dropped without being sent");
+        }
+    }
 }
 
 /// Object that can be serialized to create an APNS request.
@@ -30,6 +87,7 @@ pub struct Payload<'a> {
 /// ```no_run
 /// use apns_h2::request::notification::{NotificationBuilder, NotificationOptions};
 /// use apns_h2::request::payload::{PayloadLike, APS};
+/// use apns_h2::request::target::Target;
 /// use apns_h2::{Client, ClientConfig, DefaultNotificationBuilder, Endpoint};
 /// use serde::Serialize;
 /// use std::fs::File;
@@ -58,12 +116,12 @@ pub struct Payload<'a> {
 ///     #[serde(skip_serializing)]
 ///     options: NotificationOptions<'a>,
 ///     #[serde(skip_serializing)]
-///     device_token: &'a str,
+///     target: Target<'a>,
 /// }
 ///
 /// impl<'a> PayloadLike for Payload<'a> {
-///     fn get_device_token(&self) -> &'a str {
-///         self.device_token
+///     fn get_target(&self) -> &Target<'_> {
+///         &self.target
 ///     }
 ///     fn get_options(&self) -> &NotificationOptions<'_> {
 ///         &self.options
@@ -78,24 +136,125 @@ pub trait PayloadLike: serde::Serialize + Debug {
         Ok(serde_json::to_string(&self)?)
     }
 
-    /// Returns token for the device
-    fn get_device_token(&self) -> &str;
+    /// Returns the receiving device or broadcast channel
+    fn get_target(&self) -> &Target<'_>;
 
     /// Gets [`NotificationOptions`] for this Payload.
     fn get_options(&self) -> &NotificationOptions<'_>;
+
+    /// The interruption level of the notification, used to pick an
+    /// `apns-priority` when none is set in the options.
+    fn get_interruption_level(&self) -> Option<&InterruptionLevel> {
+        None
+    }
+
+    /// The push type matching the payload content, used to pick the size
+    /// limit when no `apns_push_type` is set in the options.
+    fn get_push_type(&self) -> PushType {
+        PushType::Alert
+    }
+
+    /// Checks the payload right before it is sent with the given
+    /// `apns-push-type`, e.g. when a payload built for one push type is sent
+    /// with another.
+    fn revalidate(&self, push_type: PushType) -> Result<(), Error> {
+        let _ = push_type;
+
+        Ok(())
+    }
+
+    /// Called when the payload is handed to the client for sending, or
+    /// exported, e.g. with [`payloads_to_ndjson`].
+    fn mark_sent(&self) {}
 }
 
 impl<'a> PayloadLike for Payload<'a> {
-    fn get_device_token(&self) -> &str {
-        &self.device_token
+    fn get_target(&self) -> &Target<'_> {
+        &self.target
     }
 
     fn get_options(&self) -> &NotificationOptions<'_> {
         &self.options
     }
+
+    fn get_interruption_level(&self) -> Option<&InterruptionLevel> {
+        self.aps.interruption_level.as_ref()
+    }
+
+    fn get_push_type(&self) -> PushType {
+        self.push_type_hint()
+    }
+
+    /// Checks the payload with [`Payload::validate_for`].
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # use apns_h2::PushType;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .title("a title")
+    ///     .build("token", Default::default());
+    ///
+    /// assert!(payload.revalidate(PushType::Alert).is_ok());
+    /// assert!(payload.revalidate(PushType::Background).is_err());
+    /// # }
+    /// ```
+    fn revalidate(&self, push_type: PushType) -> Result<(), Error> {
+        self.validate_for(push_type)
+    }
+
+    /// Keeps the payload from warning about not being sent when dropped, in
+    /// debug builds with the `tracing` feature.
+    fn mark_sent(&self) {
+        self.send_check.mark_sent();
+    }
 }
 
 impl<'a> Payload<'a> {
+    /// Creates a payload from the fields of the `aps` object, e.g. as stored
+    /// in a database, and checks it with [`validate`](Self::validate).
+    ///
+    /// The keys are the kebab-case keys APNs expects. Keys not modeled by
+    /// [`APS`] are kept in [`APS::other`] and sent as they are.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::payload::{Payload, PayloadLike};
+    /// # use serde_json::json;
+    /// # use std::collections::HashMap;
+    /// # fn main() -> Result<(), apns_h2::Error> {
+    /// let aps = HashMap::from([
+    ///     (String::from("alert"), json!({"title": "a title"})),
+    ///     (String::from("badge"), json!(3)),
+    /// ]);
+    /// let payload = Payload::from_aps_map("token", aps, Default::default())?;
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"a title\"},\"badge\":3}}",
+    ///     &payload.to_json_string()?
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_aps_map(
+        target: impl Into<Target<'a>>,
+        aps: HashMap<String, Value>,
+        options: NotificationOptions<'a>,
+    ) -> Result<Self, Error> {
+        let payload = Payload {
+            options,
+            target: target.into(),
+            aps: serde_json::from_value(Value::Object(aps.into_iter().collect()))?,
+            data: BTreeMap::new(),
+            streamed_data: BTreeMap::new(),
+            send_check: Default::default(),
+        };
+
+        payload.validate()?;
+
+        Ok(payload)
+    }
+
     /// Client-specific custom data to be added in the payload.
     /// The `root_key` defines the JSON key in the root of the request
     /// data, and `data` the object containing custom data. The `data`
@@ -155,14 +314,595 @@ impl<'a> Payload<'a> {
         root_key: impl Into<Cow<'a, str>>,
         data: &dyn Serialize,
     ) -> Result<&mut Self, Error> {
-        self.data.insert(root_key.into(), serde_json::to_value(data)?);
+        let root_key = root_key.into();
+
+        self.streamed_data.remove(&root_key);
+        self.data.insert(root_key, serde_json::to_value(data)?);
 
         Ok(self)
     }
+
+    /// Adds custom data like [`add_custom_data`](Self::add_custom_data), but
+    /// borrows `data` and serializes it straight into the payload JSON when
+    /// the payload is serialized, instead of converting it into a
+    /// [`Value`] first. Saves memory for large custom data.
+    ///
+    /// The data is not part of [`data`](Self::data) and is kept by
+    /// [`fit_to`](Self::fit_to).
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let blob = vec![1, 2, 3];
+    /// let mut payload = DefaultNotificationBuilder::new()
+    ///     .content_available()
+    ///     .build("token", Default::default());
+    ///
+    /// payload.add_custom_data_streaming("blob", &blob);
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"content-available\":1,\"mutable-content\":0},\"blob\":[1,2,3]}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn add_custom_data_streaming(
+        &mut self,
+        root_key: impl Into<Cow<'a, str>>,
+        data: &'a (dyn Serialize + Sync),
+    ) -> &mut Self {
+        let root_key = root_key.into();
+
+        self.data.remove(&root_key);
+        self.streamed_data.insert(root_key, StreamedData(data));
+
+        self
+    }
+
+    /// Like [`add_custom_data`](Self::add_custom_data), but consumes and
+    /// returns the payload for use in a single expression.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::PayloadLike;
+    /// # fn main() -> Result<(), apns_h2::Error> {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .content_available()
+    ///     .build("token", Default::default())
+    ///     .with_custom("foo_data", &["bar"])?;
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"content-available\":1,\"mutable-content\":0},\"foo_data\":[\"bar\"]}",
+    ///     &payload.to_json_string()?
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_custom(mut self, root_key: impl Into<Cow<'a, str>>, data: &dyn Serialize) -> Result<Self, Error> {
+        self.add_custom_data(root_key, data)?;
+
+        Ok(self)
+    }
+
+    /// The root keys of the custom data, sorted by name.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # fn main() -> Result<(), apns_h2::Error> {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .content_available()
+    ///     .build("token", Default::default())
+    ///     .with_custom("foo_data", &["bar"])?
+    ///     .with_custom("baz_data", &1)?;
+    ///
+    /// assert_eq!(vec!["baz_data", "foo_data"], payload.custom_keys());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn custom_keys(&self) -> Vec<&str> {
+        let mut keys = self
+            .data
+            .keys()
+            .chain(self.streamed_data.keys())
+            .map(|key| key.as_ref())
+            .collect::<Vec<_>>();
+
+        keys.sort_unstable();
+        keys
+    }
+
+    /// The size in bytes of the serialized `aps` object, excluding any custom
+    /// data.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use std::collections::HashMap;
+    /// # fn main() {
+    /// let mut payload = DefaultNotificationBuilder::new()
+    ///     .content_available()
+    ///     .build("token", Default::default());
+    ///
+    /// // {"content-available":1,"mutable-content":0}
+    /// assert_eq!(43, payload.aps_size().unwrap());
+    ///
+    /// let mut custom_data = HashMap::new();
+    /// custom_data.insert("foo", "bar");
+    /// payload.add_custom_data("foo_data", &custom_data).unwrap();
+    ///
+    /// assert_eq!(43, payload.aps_size().unwrap());
+    /// # }
+    /// ```
+    pub fn aps_size(&self) -> Result<usize, Error> {
+        Ok(serde_json::to_vec(&self.aps)?.len())
+    }
+
+    /// The size in bytes of the JSON body sent to APNs, including the custom
+    /// data, e.g. to log how close a payload is to its limit.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # fn main() -> Result<(), apns_h2::Error> {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .content_available()
+    ///     .build("token", Default::default())
+    ///     .with_custom("foo", &1)?;
+    ///
+    /// // {"aps":{"content-available":1,"mutable-content":0},"foo":1}
+    /// assert_eq!(59, payload.payload_size()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn payload_size(&self) -> Result<usize, Error> {
+        Ok(self.to_json_string()?.len())
+    }
+
+    /// A hash of the JSON body with its keys sorted, leaving out the target
+    /// and options, e.g. as a key for deduplicating or caching payloads with
+    /// the same content.
+    ///
+    /// The hash is 64-bit FNV-1a, so it stays the same across processes and
+    /// Rust versions, but it isn't meant to resist collisions on purpose.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # fn main() -> Result<(), apns_h2::Error> {
+    /// let builder = DefaultNotificationBuilder::new().body("Hi there");
+    /// let payload = builder.clone().build("token", Default::default());
+    /// let other = builder.build("other-token", Default::default());
+    ///
+    /// assert_eq!(payload.content_hash()?, other.content_hash()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn content_hash(&self) -> Result<u64, Error> {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+        // Sorted explicitly, `Value` keeps the insertion order with the
+        // `preserve_order` feature of serde_json
+        let mut value = self.to_value()?;
+        value.sort_all_objects();
+
+        let json = serde_json::to_vec(&value)?;
+
+        Ok(json.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+        }))
+    }
+
+    /// Checks the payload against the largest payload APNs accepts for the
+    /// push type, see [`PushType::max_payload_bytes`]. The
+    /// [`Client`](crate::Client) checks this before sending.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::{Error, PushType};
+    /// # fn main() -> Result<(), Error> {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .body("a".repeat(4500))
+    ///     .build("token", Default::default());
+    ///
+    /// assert!(matches!(
+    ///     payload.validate_size(PushType::Alert),
+    ///     Err(Error::PayloadTooLarge { limit: 4096, .. })
+    /// ));
+    /// assert!(payload.validate_size(PushType::Voip).is_ok());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate_size(&self, push_type: PushType) -> Result<(), Error> {
+        let size = self.payload_size()?;
+        let limit = push_type.max_payload_bytes();
+
+        if size > limit {
+            return Err(Error::PayloadTooLarge { size, limit });
+        }
+
+        Ok(())
+    }
+
+    /// The payload as a JSON value tree, including the `aps` fields and any
+    /// custom data, for serializing it into other formats.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use serde_json::json;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .event("update")
+    ///     .content_state(&json!({"score": 1}))
+    ///     .build("token", Default::default());
+    ///
+    /// let value = payload.to_value().unwrap();
+    ///
+    /// assert_eq!(json!({"score": 1}), value["aps"]["content-state"]);
+    /// # }
+    /// ```
+    pub fn to_value(&self) -> Result<Value, Error> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    /// The payload JSON with the keys of every object sorted, e.g. to compare
+    /// it with a golden file. Unlike [`to_json_string`](PayloadLike::to_json_string)
+    /// the order doesn't follow the field order of the `aps` dictionary.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .title("a title")
+    ///     .body("a body")
+    ///     .badge(1)
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"body\":\"a body\",\"title\":\"a title\"},\"badge\":1,\"mutable-content\":0}}",
+    ///     &payload.to_json_string_sorted().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn to_json_string_sorted(&self) -> Result<String, Error> {
+        let mut value = self.to_value()?;
+        value.sort_all_objects();
+
+        Ok(serde_json::to_string(&value)?)
+    }
+
+    /// Serializes the payload JSON into `buf`, clearing it first. Reusing the
+    /// same buffer avoids allocating a new string for every payload.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # fn main() {
+    /// let mut buf = Vec::new();
+    ///
+    /// for badge in 1..=2 {
+    ///     let payload = DefaultNotificationBuilder::new()
+    ///         .badge(badge)
+    ///         .build("token", Default::default());
+    ///
+    ///     payload.serialize_into(&mut buf).unwrap();
+    /// }
+    ///
+    /// assert_eq!(b"{\"aps\":{\"badge\":2,\"mutable-content\":0}}", &buf[..]);
+    /// # }
+    /// ```
+    pub fn serialize_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        self.mark_sent();
+
+        buf.clear();
+        serde_json::to_writer(&mut *buf, self)?;
+
+        Ok(())
+    }
+
+    /// Shrinks the payload until its JSON fits into `limit` bytes, in the
+    /// order given by the [`TrimPolicy`]. The alert is shortened by dropping
+    /// the subtitle and then truncating the body, ending it with `…`. Custom
+    /// data is removed key by key, starting with the last key.
+    ///
+    /// Fails with [`Error::PayloadTooLarge`] if the payload doesn't fit after
+    /// trimming everything the policy allows.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::request::payload::{PayloadLike, TrimPolicy};
+    /// # fn main() {
+    /// let mut payload = DefaultNotificationBuilder::new()
+    ///     .subtitle("a subtitle")
+    ///     .body("a body that is too long")
+    ///     .build("token", Default::default());
+    ///
+    /// payload.fit_to(64, TrimPolicy::AlertOnly).unwrap();
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"body\":\"a body that …\"},\"mutable-content\":0}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn fit_to(&mut self, limit: usize, policy: TrimPolicy) -> Result<(), Error> {
+        match policy {
+            TrimPolicy::AlertOnly => self.trim_alert(limit)?,
+            TrimPolicy::AlertThenCustomData => {
+                self.trim_alert(limit)?;
+                self.trim_custom_data(limit)?;
+            }
+            TrimPolicy::CustomDataThenAlert => {
+                self.trim_custom_data(limit)?;
+                self.trim_alert(limit)?;
+            }
+        }
+
+        let size = self.to_json_string()?.len();
+
+        if size > limit {
+            return Err(Error::PayloadTooLarge { size, limit });
+        }
+
+        Ok(())
+    }
+
+    fn trim_alert(&mut self, limit: usize) -> Result<(), Error> {
+        const ELLIPSIS: &str = "…";
+
+        if self.to_json_string()?.len() <= limit {
+            return Ok(());
+        }
+
+        let Some(APSAlert::Default(ref mut alert)) = self.aps.alert else {
+            return Ok(());
+        };
+
+        if alert.subtitle_mut().take().is_some() && self.to_json_string()?.len() <= limit {
+            return Ok(());
+        }
+
+        let Some(APSAlert::Default(ref mut alert)) = self.aps.alert else {
+            return Ok(());
+        };
+        let Some(original) = alert.body_mut().as_ref().map(|body| body.to_string()) else {
+            return Ok(());
+        };
+        let mut keep = original.len();
+
+        loop {
+            let size = self.to_json_string()?.len();
+
+            if size <= limit || keep == 0 {
+                return Ok(());
+            }
+
+            keep = keep.saturating_sub(size - limit);
+            while !original.is_char_boundary(keep) {
+                keep -= 1;
+            }
+
+            let Some(APSAlert::Default(ref mut alert)) = self.aps.alert else {
+                return Ok(());
+            };
+
+            if keep == 0 {
+                *alert.body_mut() = None;
+
+                if **alert == DefaultAlert::default() {
+                    self.aps.alert = None;
+                }
+            } else {
+                *alert.body_mut() = Some(Cow::Owned(format!("{}{}", &original[..keep], ELLIPSIS)));
+            }
+        }
+    }
+
+    fn trim_custom_data(&mut self, limit: usize) -> Result<(), Error> {
+        while self.to_json_string()?.len() > limit && self.data.pop_last().is_some() {}
+
+        Ok(())
+    }
+
+    /// Checks the payload for field combinations that contradict each other.
+    ///
+    /// A Live Activity payload combined with `content-available` is rejected,
+    /// as a silent background notification can't update a Live Activity. So
+    /// is a VoIP notification with an alert, sound or badge. Live Activity
+    /// `attributes` larger than [`MAX_ATTRIBUTES_BYTES`] fail with
+    /// [`Error::AttributesTooLarge`].
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::Error;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .content_available()
+    ///     .event("update")
+    ///     .build("token", Default::default());
+    ///
+    /// assert!(matches!(payload.validate(), Err(Error::ConflictingFields(_))));
+    /// # }
+    /// ```
+    pub fn validate(&self) -> Result<(), Error> {
+        match self.options.apns_push_type {
+            Some(PushType::Voip) => self.validate_for(PushType::Voip),
+            _ => self.validate_fields(),
+        }
+    }
+
+    /// Checks the payload with [`validate`](Self::validate) and against the
+    /// rules of the given push type, without sending it:
+    ///
+    /// * [`PushType::Alert`] needs an alert, sound or badge and no Live
+    ///   Activity fields.
+    /// * [`PushType::Background`] must be silent, see
+    ///   [`is_silent`](Self::is_silent).
+    /// * [`PushType::LiveActivity`] needs the Live Activity fields, e.g. an
+    ///   `event`. The `start` event also needs the `attributes-type`,
+    ///   `attributes`, `content-state` and `timestamp`.
+    /// * [`PushType::Voip`] can't have an alert, sound or badge.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::{Error, PushType};
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .title("a title")
+    ///     .build("token", Default::default());
+    ///
+    /// assert!(payload.validate_for(PushType::Alert).is_ok());
+    /// assert!(matches!(
+    ///     payload.validate_for(PushType::Background),
+    ///     Err(Error::ConflictingFields(_))
+    /// ));
+    /// # }
+    /// ```
+    pub fn validate_for(&self, push_type: PushType) -> Result<(), Error> {
+        self.validate_fields()?;
+
+        let message = match push_type {
+            PushType::Alert if !self.has_visible_content() => "An alert notification needs an alert, sound or badge.",
+            PushType::Alert if self.aps.has_live_activity_fields() => {
+                "An alert notification can't have Live Activity fields, use the liveactivity push type."
+            }
+            PushType::Background if self.has_visible_content() => {
+                "A background notification can't have an alert, sound or badge."
+            }
+            PushType::Background if self.aps.content_available != Some(1) => {
+                "A background notification needs content-available."
+            }
+            PushType::LiveActivity if !self.aps.has_live_activity_fields() => {
+                "A Live Activity notification needs an event and content state."
+            }
+            PushType::LiveActivity if self.aps.event.as_deref() == Some("start") && !self.aps.is_complete_start() => {
+                "Starting a Live Activity needs attributes-type, attributes, content-state and timestamp."
+            }
+            PushType::Voip if self.has_visible_content() => "A VoIP notification can't have an alert, sound or badge.",
+            _ => return Ok(()),
+        };
+
+        Err(Error::ConflictingFields(String::from(message)))
+    }
+
+    /// The checks of [`validate`](Self::validate) independent of the push type.
+    fn validate_fields(&self) -> Result<(), Error> {
+        if self.aps.content_available == Some(1) && self.aps.has_live_activity_fields() {
+            return Err(Error::ConflictingFields(String::from(
+                "A Live Activity payload can't be combined with content-available.",
+            )));
+        }
+
+        if let Some(ref attributes) = self.aps.attributes {
+            let size = serde_json::to_vec(attributes)?.len();
+
+            if size > MAX_ATTRIBUTES_BYTES {
+                return Err(Error::AttributesTooLarge {
+                    size,
+                    limit: MAX_ATTRIBUTES_BYTES,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the payload shows an alert, plays a sound or sets the badge.
+    fn has_visible_content(&self) -> bool {
+        self.aps.alert.is_some() || self.aps.sound.is_some() || self.aps.badge.is_some()
+    }
+
+    /// Whether the payload sets a `thread-id` without any content the system
+    /// could group by it.
+    pub(crate) fn has_ungrouped_thread_id(&self) -> bool {
+        self.aps.thread_id.is_some() && !self.has_visible_content()
+    }
+
+    /// Whether the payload is a silent background notification, waking the
+    /// app with `content-available` without an alert, sound or badge.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .content_available()
+    ///     .build("token", Default::default());
+    ///
+    /// assert!(payload.is_silent());
+    /// # }
+    /// ```
+    pub fn is_silent(&self) -> bool {
+        self.aps.content_available == Some(1) && !self.has_visible_content()
+    }
+
+    /// The push type matching the payload content: [`PushType::LiveActivity`]
+    /// for Live Activity updates, [`PushType::Background`] for silent
+    /// notifications and [`PushType::Alert`] otherwise.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use apns_h2::PushType;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .title("a title")
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(PushType::Alert, payload.push_type_hint());
+    /// # }
+    /// ```
+    pub fn push_type_hint(&self) -> PushType {
+        if self.aps.has_live_activity_fields() {
+            PushType::LiveActivity
+        } else if self.is_silent() {
+            PushType::Background
+        } else {
+            PushType::Alert
+        }
+    }
+}
+
+/// Serializes the payloads as newline-delimited JSON, one payload per line,
+/// e.g. for handing a batch to another sender. Only the JSON body is
+/// written, not the target or options.
+///
+/// ```rust
+/// # use apns_h2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+/// # use apns_h2::request::payload::payloads_to_ndjson;
+/// # fn main() -> Result<(), apns_h2::Error> {
+/// let payloads = [
+///     DefaultNotificationBuilder::new().badge(1).build("token", Default::default()),
+///     DefaultNotificationBuilder::new().badge(2).build("token", Default::default()),
+/// ];
+///
+/// assert_eq!(
+///     "{\"aps\":{\"badge\":1,\"mutable-content\":0}}\n{\"aps\":{\"badge\":2,\"mutable-content\":0}}\n",
+///     payloads_to_ndjson(&payloads)?
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn payloads_to_ndjson<T: PayloadLike>(payloads: &[T]) -> Result<String, Error> {
+    let mut ndjson = String::new();
+
+    for payload in payloads {
+        payload.mark_sent();
+        ndjson.push_str(&payload.to_json_string()?);
+        ndjson.push('\n');
+    }
+
+    Ok(ndjson)
+}
+
+/// What [`Payload::fit_to`] may remove from a payload to make it fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimPolicy {
+    /// Only shorten the alert.
+    AlertOnly,
+    /// Shorten the alert, then remove custom data if still too large.
+    AlertThenCustomData,
+    /// Remove custom data, then shorten the alert if still too large.
+    CustomDataThenAlert,
 }
 
 /// The pre-defined notification data.
-#[derive(Serialize, Default, Debug, Clone)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
 #[serde(rename_all = "kebab-case")]
 #[allow(clippy::upper_case_acronyms)]
 pub struct APS<'a> {
@@ -196,11 +936,21 @@ pub struct APS<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mutable_content: Option<u8>,
 
+    /// The identifier of the window brought forward when the user opens the
+    /// notification.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_content_id: Option<Cow<'a, str>>,
+
     /// Interruption level for the notification. Controls how the notification
     /// is presented to the user and what system settings it can bypass.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub interruption_level: Option<InterruptionLevel>,
 
+    /// How relevant the notification is compared to the other notifications
+    /// of the app, from 0 to 1, for featuring it in a notification summary.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relevance_score: Option<f64>,
+
     /// The date when the system should automatically remove the notification.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dismissal_date: Option<u64>,
@@ -212,6 +962,11 @@ pub struct APS<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timestamp: Option<u64>,
 
+    /// Live Activity: The date the content becomes outdated and the system
+    /// shows it as stale, distinct from the `dismissal_date`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stale_date: Option<u64>,
+
     /// Live Activity: Event type ("start" to begin a Live Activity).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub event: Option<Cow<'a, str>>,
@@ -235,9 +990,39 @@ pub struct APS<'a> {
     /// Live Activity: Set to 1 to request a new push token for iOS 18+ token-based updates.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub input_push_token: Option<u8>,
+
+    /// Keys of the `aps` object not modeled by the fields above, e.g. read
+    /// by [`Payload::from_aps_map`], sent as they are. Must not repeat the
+    /// key of another field.
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, Value>,
+}
+
+impl APS<'_> {
+    /// Whether any of the fields only used by Live Activities is set.
+    pub(crate) fn has_live_activity_fields(&self) -> bool {
+        self.event.is_some()
+            || self.content_state.is_some()
+            || self.stale_date.is_some()
+            || self.attributes_type.is_some()
+            || self.attributes.is_some()
+            || self.input_push_channel.is_some()
+            || self.input_push_token.is_some()
+    }
+
+    /// Whether all fields needed to start a Live Activity are set.
+    fn is_complete_start(&self) -> bool {
+        self.attributes_type.is_some()
+            && self.attributes.is_some()
+            && self.content_state.is_some()
+            && self.timestamp.is_some()
+    }
 }
 
 /// Different notification content types.
+///
+/// An alert that a [`DefaultAlert`] can't hold without losing keys is
+/// deserialized as [`Raw`](Self::Raw).
 #[derive(Serialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum APSAlert<'a> {
@@ -245,20 +1030,39 @@ pub enum APSAlert<'a> {
     Default(Box<DefaultAlert<'a>>),
     /// Safari web push notification
     WebPush(WebPushAlert<'a>),
+    /// An alert given as raw JSON, for shapes not modeled by the other
+    /// variants
+    Raw(Value),
+}
+
+impl<'de> serde::Deserialize<'de> for APSAlert<'_> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = <Value as serde::Deserialize>::deserialize(deserializer)?;
+
+        // A `DefaultAlert` ignores keys it doesn't know, so it's only used if
+        // it serializes back to the same alert
+        match serde_json::from_value::<DefaultAlert<'static>>(value.clone()) {
+            Ok(alert) if serde_json::to_value(&alert).is_ok_and(|alert| alert == value) => {
+                Ok(APSAlert::Default(Box::new(alert)))
+            }
+            _ => Ok(APSAlert::Raw(value)),
+        }
+    }
 }
 
 /// Different notification sound types.
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum APSSound<'a> {
-    /// A critical notification (supported only on >= iOS 12)
+    /// A critical notification (supported only on >= iOS 12), or a named
+    /// sound sent as an object
     Critical(DefaultSound<'a>),
     /// Name for a notification sound
     Sound(Cow<'a, str>),
 }
 
 /// Interruption level for notification delivery and presentation.
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub enum InterruptionLevel {
     /// The system presents the notification immediately, lights up the screen, and can play a sound.
@@ -271,6 +1075,46 @@ pub enum InterruptionLevel {
     TimeSensitive,
 }
 
+impl InterruptionLevel {
+    /// The priority a notification with this interruption level is sent with
+    /// if no `apns_priority` is set. Time sensitive and critical notifications
+    /// are delayed otherwise.
+    pub(crate) fn default_priority(&self) -> Option<Priority> {
+        match self {
+            InterruptionLevel::Critical | InterruptionLevel::TimeSensitive => Some(Priority::High),
+            InterruptionLevel::Active | InterruptionLevel::Passive => None,
+        }
+    }
+}
+
+/// Removes the keys of the `aps` object and its alert that are set to a
+/// default value, for [`ClientConfig::minimize_payload`](crate::ClientConfig::minimize_payload).
+///
+/// The `badge` is kept, as 0 removes the badge, and so are the `content-state`
+/// and `attributes` of Live Activities, which are defined by the app.
+pub(crate) fn minimize_aps(payload: &mut Value) {
+    fn is_default(value: &Value) -> bool {
+        match value {
+            Value::Null | Value::Bool(false) => true,
+            Value::Number(number) => number.as_f64() == Some(0.0),
+            Value::String(string) => string.is_empty(),
+            Value::Array(array) => array.is_empty(),
+            Value::Object(object) => object.is_empty(),
+            Value::Bool(true) => false,
+        }
+    }
+
+    let Some(Value::Object(aps)) = payload.get_mut("aps") else {
+        return;
+    };
+
+    if let Some(Value::Object(alert)) = aps.get_mut("alert") {
+        alert.retain(|_, value| !is_default(value));
+    }
+
+    aps.retain(|key, value| matches!(key.as_str(), "badge" | "content-state" | "attributes") || !is_default(value));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -322,6 +1166,50 @@ mod tests {
         assert!(json.contains("\"dismissal-date\":1672531200"));
     }
 
+    #[test]
+    fn test_content_available_with_live_activity_conflicts() {
+        let content_state = json!({"currentHealthLevel": 100});
+
+        let result = DefaultNotificationBuilder::new()
+            .content_available()
+            .event("update")
+            .content_state(&content_state)
+            .try_build("test-token", Default::default());
+
+        assert!(matches!(result, Err(Error::ConflictingFields(_))));
+
+        let result = DefaultNotificationBuilder::new()
+            .event("update")
+            .content_state(&content_state)
+            .try_build("test-token", Default::default());
+
+        assert!(result.is_ok());
+
+        let result = DefaultNotificationBuilder::new()
+            .content_available()
+            .try_build("test-token", Default::default());
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_voip_with_alert_conflicts() {
+        let voip = || NotificationOptions {
+            apns_push_type: Some(PushType::Voip),
+            ..Default::default()
+        };
+
+        let result = DefaultNotificationBuilder::new()
+            .title("a title")
+            .try_build("test-token", voip());
+
+        assert!(matches!(result, Err(Error::ConflictingFields(_))));
+
+        let result = DefaultNotificationBuilder::new().try_build("test-token", voip());
+
+        assert!(result.is_ok());
+    }
+
     #[test]
     fn test_live_activity_payload_serialization() {
         use serde_json::json;
@@ -386,4 +1274,431 @@ mod tests {
 
         assert!(json_str.contains("\"input-push-token\":1"));
     }
+
+    #[test]
+    fn test_live_activity_update_with_alert() {
+        let payload = DefaultNotificationBuilder::new()
+            .event("update")
+            .timestamp(1234)
+            .content_state(&json!({"score": 2}))
+            .title("Goal!")
+            .body("The home team scored")
+            .sound("default")
+            .build("test-token", Default::default());
+
+        assert_eq!(
+            json!({
+                "aps": {
+                    "alert": {"title": "Goal!", "body": "The home team scored"},
+                    "sound": "default",
+                    "mutable-content": 0,
+                    "timestamp": 1234,
+                    "event": "update",
+                    "content-state": {"score": 2}
+                }
+            }),
+            payload.to_value().unwrap()
+        );
+        assert!(payload.validate().is_ok());
+        assert_eq!(PushType::LiveActivity, payload.push_type_hint());
+    }
+
+    #[test]
+    fn test_attributes_too_large() {
+        let start = |text: String| {
+            DefaultNotificationBuilder::new()
+                .event("start")
+                .attributes_type("MatchAttributes")
+                .attributes(&json!({ "text": text }))
+                .try_build("test-token", Default::default())
+        };
+
+        assert!(start("a".repeat(100)).is_ok());
+        assert!(matches!(
+            start("a".repeat(MAX_ATTRIBUTES_BYTES)),
+            Err(Error::AttributesTooLarge { size, limit: MAX_ATTRIBUTES_BYTES }) if size == MAX_ATTRIBUTES_BYTES + 11
+        ));
+    }
+
+    #[test]
+    fn test_validate_for_push_type() {
+        let alert = DefaultNotificationBuilder::new()
+            .title("a title")
+            .build("test-token", Default::default());
+        let silent = DefaultNotificationBuilder::new()
+            .content_available()
+            .build("test-token", Default::default());
+        let live_activity = DefaultNotificationBuilder::new()
+            .event("update")
+            .timestamp(1234)
+            .content_state(&json!({"score": 1}))
+            .build("test-token", Default::default());
+        let incomplete_start = DefaultNotificationBuilder::new()
+            .event("start")
+            .content_state(&json!({"score": 0}))
+            .build("test-token", Default::default());
+        let empty = DefaultNotificationBuilder::new().build("test-token", Default::default());
+
+        assert!(alert.validate_for(PushType::Alert).is_ok());
+        assert!(silent.validate_for(PushType::Background).is_ok());
+        assert!(live_activity.validate_for(PushType::LiveActivity).is_ok());
+        assert!(empty.validate_for(PushType::Voip).is_ok());
+
+        let error = |payload: &Payload, push_type| match payload.validate_for(push_type) {
+            Err(Error::ConflictingFields(message)) => message,
+            result => panic!("unexpected result {result:?}"),
+        };
+
+        assert_eq!(
+            "A background notification can't have an alert, sound or badge.",
+            error(&alert, PushType::Background)
+        );
+        assert_eq!(
+            "A background notification needs content-available.",
+            error(&empty, PushType::Background)
+        );
+        assert_eq!(
+            "An alert notification needs an alert, sound or badge.",
+            error(&silent, PushType::Alert)
+        );
+        assert_eq!(
+            "A Live Activity notification needs an event and content state.",
+            error(&alert, PushType::LiveActivity)
+        );
+        assert_eq!(
+            "Starting a Live Activity needs attributes-type, attributes, content-state and timestamp.",
+            error(&incomplete_start, PushType::LiveActivity)
+        );
+        assert_eq!(
+            "A VoIP notification can't have an alert, sound or badge.",
+            error(&alert, PushType::Voip)
+        );
+    }
+
+    #[test]
+    fn test_push_type_hint() {
+        let silent = DefaultNotificationBuilder::new()
+            .content_available()
+            .build("test-token", Default::default());
+
+        assert!(silent.is_silent());
+        assert_eq!(PushType::Background, silent.push_type_hint());
+
+        let with_badge = DefaultNotificationBuilder::new()
+            .content_available()
+            .badge(1)
+            .build("test-token", Default::default());
+
+        assert!(!with_badge.is_silent());
+        assert_eq!(PushType::Alert, with_badge.push_type_hint());
+
+        let live_activity = DefaultNotificationBuilder::new()
+            .event("update")
+            .timestamp(1234)
+            .build("test-token", Default::default());
+
+        assert!(!live_activity.is_silent());
+        assert_eq!(PushType::LiveActivity, live_activity.push_type_hint());
+    }
+
+    #[test]
+    fn test_fit_to() {
+        let build = || {
+            DefaultNotificationBuilder::new()
+                .subtitle("a subtitle")
+                .body("a body with ünïcödé characters")
+                .build("test-token", Default::default())
+                .with_custom("a", &"some custom data")
+                .unwrap()
+                .with_custom("b", &"more custom data")
+                .unwrap()
+        };
+
+        let mut payload = build();
+        payload.fit_to(110, TrimPolicy::CustomDataThenAlert).unwrap();
+
+        assert_eq!(
+            json!({
+                "aps": {
+                    "alert": {"subtitle": "a subtitle", "body": "a body with ünïcödé characters"},
+                    "mutable-content": 0,
+                },
+            }),
+            serde_json::to_value(&payload).unwrap()
+        );
+
+        let mut payload = build();
+        payload.fit_to(90, TrimPolicy::CustomDataThenAlert).unwrap();
+
+        assert_eq!(
+            json!({
+                "aps": {
+                    "alert": {"body": "a body with ünïcödé characters"},
+                    "mutable-content": 0,
+                },
+            }),
+            serde_json::to_value(&payload).unwrap()
+        );
+
+        let mut payload = build();
+        payload.fit_to(100, TrimPolicy::AlertThenCustomData).unwrap();
+        let value = serde_json::to_value(&payload).unwrap();
+
+        assert!(payload.to_json_string().unwrap().len() <= 100);
+        assert!(value["aps"]["alert"]["body"].as_str().unwrap().ends_with("…"));
+        assert_eq!(json!("more custom data"), value["b"]);
+
+        let mut payload = build();
+        payload.fit_to(60, TrimPolicy::AlertThenCustomData).unwrap();
+
+        assert_eq!(
+            json!({
+                "aps": {"mutable-content": 0},
+                "a": "some custom data",
+            }),
+            serde_json::to_value(&payload).unwrap()
+        );
+
+        let mut payload = build();
+        let result = payload.fit_to(10, TrimPolicy::AlertOnly);
+
+        assert!(matches!(result, Err(Error::PayloadTooLarge { limit: 10, .. })));
+    }
+
+    #[test]
+    fn test_minimize_aps() {
+        let mut payload = json!({
+            "aps": {
+                "alert": {"title": "a title", "loc-args": [], "subtitle": ""},
+                "badge": 0,
+                "mutable-content": 0,
+                "content-available": 0,
+                "relevance-score": 0.0,
+                "sound": "default",
+                "content-state": {},
+            },
+            "custom": {"count": 0},
+        });
+
+        minimize_aps(&mut payload);
+
+        assert_eq!(
+            json!({
+                "aps": {
+                    "alert": {"title": "a title"},
+                    "badge": 0,
+                    "sound": "default",
+                    "content-state": {},
+                },
+                "custom": {"count": 0},
+            }),
+            payload
+        );
+    }
+
+    #[test]
+    #[cfg(all(debug_assertions, feature = "tracing"))]
+    fn test_send_check() {
+        use std::sync::atomic::Ordering;
+
+        let payload = DefaultNotificationBuilder::new()
+            .title("a title")
+            .build("test-token", Default::default());
+
+        assert!(!payload.send_check.sent.load(Ordering::Relaxed));
+
+        payload.mark_sent();
+        let clone = payload.clone();
+
+        assert!(payload.send_check.sent.load(Ordering::Relaxed));
+        assert!(!clone.send_check.sent.load(Ordering::Relaxed));
+
+        // Exporting counts as sending the payload
+        assert!(payloads_to_ndjson(std::slice::from_ref(&clone)).is_ok());
+        assert!(clone.send_check.sent.load(Ordering::Relaxed));
+
+        let (logs, _subscriber) = crate::client::tests::capture_logs();
+        let measured = payload.clone();
+
+        // Measuring or hashing a payload doesn't send it
+        assert!(measured.payload_size().is_ok());
+        assert!(measured.content_hash().is_ok());
+        assert!(!measured.send_check.sent.load(Ordering::Relaxed));
+
+        drop(measured);
+
+        let logs = String::from_utf8(logs.lock().clone()).unwrap();
+
+        assert_eq!(1, logs.matches("dropped without being sent").count(), "{logs}");
+    }
+
+    #[test]
+    fn test_custom_data_streaming() {
+        let blob = vec![String::from("a"); 3];
+        let mut payload = DefaultNotificationBuilder::new()
+            .content_available()
+            .build("test-token", Default::default());
+
+        payload.add_custom_data("blob", &1).unwrap();
+        payload.add_custom_data("other", &2).unwrap();
+        payload.add_custom_data_streaming("blob", &blob);
+        payload.add_custom_data_streaming("another", &blob);
+
+        assert_eq!(vec!["another", "blob", "other"], payload.custom_keys());
+        assert_eq!(
+            json!({"aps": {"content-available": 1, "mutable-content": 0}, "another": ["a", "a", "a"], "blob": ["a", "a", "a"], "other": 2}),
+            serde_json::to_value(&payload).unwrap()
+        );
+
+        payload.add_custom_data("blob", &4).unwrap();
+
+        assert_eq!(
+            json!({"aps": {"content-available": 1, "mutable-content": 0}, "another": ["a", "a", "a"], "blob": 4, "other": 2}),
+            serde_json::to_value(&payload).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_payloads_to_ndjson() {
+        let payloads = [
+            DefaultNotificationBuilder::new()
+                .body("line one\nline two")
+                .build("test-token", Default::default()),
+            DefaultNotificationBuilder::new()
+                .content_available()
+                .build("test-token", Default::default()),
+        ];
+
+        let ndjson = payloads_to_ndjson(&payloads).unwrap();
+        let lines = ndjson.lines().collect::<Vec<_>>();
+
+        assert_eq!(2, lines.len());
+        assert_eq!(payloads[0].to_json_string().unwrap(), lines[0]);
+        assert_eq!(payloads[1].to_json_string().unwrap(), lines[1]);
+        assert_eq!("", payloads_to_ndjson::<Payload>(&[]).unwrap());
+    }
+
+    #[test]
+    fn test_validate_size() {
+        let mut payload = DefaultNotificationBuilder::new()
+            .content_available()
+            .build("test-token", Default::default());
+        let padding = 4096 - payload.payload_size().unwrap() - r#","pad":"""#.len();
+
+        payload.add_custom_data("pad", &"a".repeat(padding)).unwrap();
+
+        assert_eq!(4096, payload.payload_size().unwrap());
+        assert!(payload.validate_size(PushType::Background).is_ok());
+
+        payload.add_custom_data("pad", &"a".repeat(padding + 1)).unwrap();
+
+        assert!(matches!(
+            payload.validate_size(PushType::Background),
+            Err(Error::PayloadTooLarge {
+                size: 4097,
+                limit: 4096
+            })
+        ));
+    }
+
+    #[test]
+    fn test_from_aps_map() {
+        let aps_map = |payload: &Payload<'_>| {
+            let Value::Object(aps) = serde_json::to_value(&payload.aps).unwrap() else {
+                unreachable!();
+            };
+            aps.into_iter().collect::<HashMap<_, _>>()
+        };
+
+        let payload = DefaultNotificationBuilder::new()
+            .title("a title")
+            .loc_args(&["one", "two"])
+            .sound("ping.caf")
+            .critical(true, Some(0.5))
+            .badge(0)
+            .time_sensitive_interruption_level()
+            .relevance_score(0.5)
+            .build("test-token", Default::default());
+        let rebuilt = Payload::from_aps_map("test-token", aps_map(&payload), Default::default()).unwrap();
+
+        assert_eq!(payload.to_json_string().unwrap(), rebuilt.to_json_string().unwrap());
+
+        // Alerts of other shapes are kept as they are
+        for alert in [
+            json!("Hello"),
+            json!({"title": "Hello", "body": "World", "action": "View"}),
+        ] {
+            let aps = HashMap::from([(String::from("alert"), alert.clone())]);
+            let payload = Payload::from_aps_map("test-token", aps, Default::default()).unwrap();
+
+            assert!(matches!(payload.aps.alert, Some(APSAlert::Raw(_))));
+            assert_eq!(
+                json!({"aps": {"alert": alert}}),
+                serde_json::to_value(&payload).unwrap()
+            );
+        }
+
+        // Keys the crate doesn't model are sent as they are
+        let unknown = HashMap::from([
+            (String::from("badge"), json!(1)),
+            (String::from("filter-criteria"), json!("work")),
+        ]);
+        let payload = Payload::from_aps_map("test-token", unknown, Default::default()).unwrap();
+
+        assert_eq!(Some(&json!("work")), payload.aps.other.get("filter-criteria"));
+        assert_eq!(
+            json!({"aps": {"badge": 1, "filter-criteria": "work"}}),
+            serde_json::to_value(&payload).unwrap()
+        );
+        assert!(serde_json::from_value::<DefaultAlert<'_>>(json!({"title": "Hello", "action": "View"})).is_ok());
+
+        let conflicting = HashMap::from([
+            (String::from("content-available"), json!(1)),
+            (String::from("event"), json!("update")),
+        ]);
+
+        assert!(matches!(
+            Payload::from_aps_map("test-token", conflicting, Default::default()),
+            Err(Error::ConflictingFields(_))
+        ));
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let builder = DefaultNotificationBuilder::new().badge(1);
+        let payload = builder.clone().build("a-token", Default::default());
+        let hash = payload.content_hash().unwrap();
+
+        assert_eq!(6989306157205771975, hash);
+
+        let other_target = builder.clone().build(
+            "another-token",
+            NotificationOptions {
+                apns_topic: Some("com.example.app"),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(hash, other_target.content_hash().unwrap());
+
+        let zulu = 1;
+        let mut streamed = builder.clone().build("a-token", Default::default());
+        streamed.add_custom_data_streaming("zulu", &zulu);
+        streamed.add_custom_data("alpha", &2).unwrap();
+
+        let mut plain = builder.clone().build("another-token", Default::default());
+        plain.add_custom_data("alpha", &2).unwrap();
+        plain.add_custom_data("zulu", &1).unwrap();
+
+        assert_eq!(plain.content_hash().unwrap(), streamed.content_hash().unwrap());
+        assert_ne!(hash, plain.content_hash().unwrap());
+        assert_ne!(
+            hash,
+            builder
+                .badge(2)
+                .build("a-token", Default::default())
+                .content_hash()
+                .unwrap()
+        );
+    }
 }
@@ -0,0 +1,228 @@
+/// The recipient of a notification
+use crate::error::Error;
+use base64::prelude::*;
+use std::borrow::Cow;
+use std::fmt;
+
+/// Where a notification is delivered to.
+///
+/// Regular notifications are sent to a single device token. Live Activity
+/// updates on iOS 18 and later can also be broadcast to a channel, reaching
+/// every device subscribed to it.
+///
+/// Strings convert into a [`Target::Device`], so existing code passing a
+/// device token to [`NotificationBuilder::build`](crate::NotificationBuilder::build)
+/// keeps working. Use [`DeviceToken`] and [`ChannelId`] to validate the value
+/// and to make sure it can only be routed to the matching target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target<'a> {
+    /// A device token, sent to `/3/device/<device token>`.
+    Device(Cow<'a, str>),
+    /// A broadcast channel ID, sent to `/4/broadcasts/apps/<apns_topic>` with
+    /// the `apns-channel-id` header.
+    Channel(Cow<'a, str>),
+}
+
+impl<'a> Target<'a> {
+    /// The device token or channel ID.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Target::Device(token) => token,
+            Target::Channel(channel_id) => channel_id,
+        }
+    }
+}
+
+impl fmt::Display for Target<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<'a> From<&'a str> for Target<'a> {
+    fn from(device_token: &'a str) -> Self {
+        Target::Device(Cow::Borrowed(device_token))
+    }
+}
+
+impl<'a> From<&'a String> for Target<'a> {
+    fn from(device_token: &'a String) -> Self {
+        Target::Device(Cow::Borrowed(device_token))
+    }
+}
+
+impl From<String> for Target<'_> {
+    fn from(device_token: String) -> Self {
+        Target::Device(Cow::Owned(device_token))
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for Target<'a> {
+    fn from(device_token: Cow<'a, str>) -> Self {
+        Target::Device(device_token)
+    }
+}
+
+impl<'a> From<DeviceToken<'a>> for Target<'a> {
+    fn from(device_token: DeviceToken<'a>) -> Self {
+        Target::Device(device_token.0)
+    }
+}
+
+impl<'a> From<ChannelId<'a>> for Target<'a> {
+    fn from(channel_id: ChannelId<'a>) -> Self {
+        Target::Channel(channel_id.0)
+    }
+}
+
+/// A hex encoded device token, as received by the app when registering for
+/// remote notifications.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceToken<'a>(Cow<'a, str>);
+
+impl<'a> DeviceToken<'a> {
+    /// Creates a device token, failing if the value isn't a non-empty hex
+    /// string.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::target::DeviceToken;
+    /// # fn main() {
+    /// assert!(DeviceToken::new("a1b2c3d4").is_ok());
+    /// assert!(DeviceToken::new("not hex").is_err());
+    /// # }
+    /// ```
+    pub fn new(device_token: impl Into<Cow<'a, str>>) -> Result<Self, Error> {
+        let device_token = device_token.into();
+
+        if device_token.is_empty() || device_token.len() % 2 != 0 {
+            return Err(Error::InvalidTarget(String::from(
+                "The device token must be a non-empty hex string of even length.",
+            )));
+        }
+
+        if !device_token.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(Error::InvalidTarget(String::from(
+                "The device token must only contain hex digits.",
+            )));
+        }
+
+        Ok(DeviceToken(device_token))
+    }
+
+    /// The hex encoded device token.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The raw bytes of the device token, e.g. to store it compactly.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::target::DeviceToken;
+    /// # fn main() -> Result<(), apns_h2::Error> {
+    /// let device_token = DeviceToken::new("a1B2c3d4")?;
+    ///
+    /// assert_eq!(vec![0xa1, 0xb2, 0xc3, 0xd4], device_token.to_bytes());
+    /// assert_eq!("a1b2c3d4", DeviceToken::from_bytes(&device_token.to_bytes())?.as_str());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        fn hex_value(digit: u8) -> u8 {
+            match digit {
+                b'0'..=b'9' => digit - b'0',
+                b'a'..=b'f' => digit - b'a' + 10,
+                _ => digit - b'A' + 10,
+            }
+        }
+
+        // The token was checked to be hex of even length in `new`
+        self.0
+            .as_bytes()
+            .chunks_exact(2)
+            .map(|pair| hex_value(pair[0]) << 4 | hex_value(pair[1]))
+            .collect()
+    }
+
+    /// Creates a device token from its raw bytes, encoded as lowercase hex.
+    /// Fails if there are no bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<DeviceToken<'static>, Error> {
+        let hex = bytes.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+
+        DeviceToken::new(hex)
+    }
+}
+
+/// A base64 encoded broadcast channel ID, as returned by the APNs channel
+/// management API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelId<'a>(Cow<'a, str>);
+
+impl<'a> ChannelId<'a> {
+    /// Creates a channel ID, failing if the value isn't a non-empty base64
+    /// string.
+    ///
+    /// ```rust
+    /// # use apns_h2::request::target::ChannelId;
+    /// # fn main() {
+    /// assert!(ChannelId::new("dHN0LXNyY2gtY2hubA==").is_ok());
+    /// assert!(ChannelId::new("not base64").is_err());
+    /// # }
+    /// ```
+    pub fn new(channel_id: impl Into<Cow<'a, str>>) -> Result<Self, Error> {
+        let channel_id = channel_id.into();
+
+        if channel_id.is_empty() || BASE64_STANDARD.decode(channel_id.as_bytes()).is_err() {
+            return Err(Error::InvalidTarget(String::from(
+                "The channel ID must be a non-empty base64 string.",
+            )));
+        }
+
+        Ok(ChannelId(channel_id))
+    }
+
+    /// The base64 encoded channel ID.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_token_validation() {
+        assert!(DeviceToken::new("0123456789abcdefABCDEF").is_ok());
+        assert!(DeviceToken::new("").is_err());
+        assert!(DeviceToken::new("abc").is_err());
+        assert!(DeviceToken::new("zz").is_err());
+        assert!(DeviceToken::new("dHN0LXNyY2gtY2hubA==").is_err());
+    }
+
+    #[test]
+    fn test_device_token_bytes() {
+        let hex = "00ff10a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d";
+        let bytes = DeviceToken::new(hex).unwrap().to_bytes();
+
+        assert_eq!(32, bytes.len());
+        assert_eq!(&[0x00, 0xff, 0x10], &bytes[..3]);
+        assert_eq!(hex, DeviceToken::from_bytes(&bytes).unwrap().as_str());
+        assert!(DeviceToken::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn test_channel_id_validation() {
+        assert!(ChannelId::new("dHN0LXNyY2gtY2hubA==").is_ok());
+        assert!(ChannelId::new("").is_err());
+        assert!(ChannelId::new("not base64!").is_err());
+    }
+
+    #[test]
+    fn test_typed_targets() {
+        let device_token = DeviceToken::new("a1b2c3d4").unwrap();
+        let channel_id = ChannelId::new("dHN0LXNyY2gtY2hubA==").unwrap();
+
+        assert_eq!(Target::Device("a1b2c3d4".into()), Target::from(device_token));
+        assert_eq!(Target::Channel("dHN0LXNyY2gtY2hubA==".into()), Target::from(channel_id));
+    }
+}
@@ -1,6 +1,7 @@
 //! The APNs response types
 
 use std::fmt;
+use std::time::{Duration, SystemTime};
 
 /// The response data from APNs.
 #[derive(Debug)]
@@ -9,7 +10,8 @@ pub struct Response {
     pub error: Option<ErrorBody>,
 
     /// Is the value defined in the `NotificationOptions` or a new Uuid
-    /// generated by APNs.
+    /// generated by APNs. Always the value APNs answered with, a different
+    /// value than the one sent is logged with the `tracing` feature.
     pub apns_id: Option<String>,
 
     /// A unique identifier that is only available in the Development environment.
@@ -28,6 +30,135 @@ pub struct Response {
     /// * 500 Internal server error.
     /// * 503 The server is shutting down and unavailable.
     pub code: u16,
+
+    /// How APNs handles the accepted notification. Only available for
+    /// successful responses.
+    ///
+    /// APNs answers with the same status for every accepted notification, so
+    /// this is derived from the `apns_expiration` sent with the request.
+    pub delivery: Option<Delivery>,
+
+    /// A new connection to APNs was opened to send the notification, either
+    /// for the first send or because the previous connection was dropped.
+    pub reconnected: bool,
+}
+
+/// The unparsed response from APNs, as returned by
+/// [`Client::send_verbose`](crate::Client::send_verbose).
+#[derive(Debug)]
+pub struct VerboseResponse {
+    /// The HTTP status code.
+    pub status: http::StatusCode,
+
+    /// Every header APNs sent, including `apns-id` and `apns-unique-id`.
+    pub headers: http::HeaderMap,
+
+    /// The response body, empty for successful notifications.
+    pub body: String,
+}
+
+/// The outcome of sending a notification to many device tokens with
+/// [`Client::run_campaign`](crate::Client::run_campaign).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CampaignReport {
+    /// Notifications accepted by APNs
+    pub delivered: usize,
+
+    /// Notifications that failed after all retries, except for invalid
+    /// device tokens
+    pub failed: usize,
+
+    /// Notifications rejected because APNs reported the device token as
+    /// unregistered or bad
+    pub purged: usize,
+}
+
+/// What was sent for a notification, as returned by
+/// [`Client::send_audited`](crate::Client::send_audited) for audit logs,
+/// also when the send failed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// The device token or channel ID, with all but the first and last four
+    /// characters masked.
+    pub target: String,
+
+    /// The request headers, without the `authorization` header.
+    pub headers: Vec<(String, String)>,
+
+    /// The size of the request body in bytes.
+    pub body_bytes: usize,
+
+    /// The `apns-id` returned by APNs, or the one sent if there was no answer.
+    pub apns_id: Option<String>,
+
+    /// When the notification was first sent.
+    pub sent_at: Option<SystemTime>,
+
+    /// The time from sending until the final answer, including retries.
+    pub duration: Duration,
+
+    /// How often the notification was sent.
+    pub attempts: u32,
+}
+
+impl AuditRecord {
+    pub(crate) fn new(target: &str) -> Self {
+        let chars = target.chars().count();
+        let target = target
+            .chars()
+            .enumerate()
+            .map(|(i, c)| if i < 4 || i + 4 >= chars { c } else { '*' })
+            .collect();
+
+        Self {
+            target,
+            ..Default::default()
+        }
+    }
+
+    /// Records a request as sent now.
+    pub(crate) fn record_request<B>(&mut self, request: &http::Request<B>) {
+        self.headers = request
+            .headers()
+            .iter()
+            .filter(|(name, _)| *name != http::header::AUTHORIZATION)
+            .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+            .collect();
+        self.body_bytes = request
+            .headers()
+            .get(http::header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default();
+        self.apns_id = request
+            .headers()
+            .get("apns-id")
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+        self.sent_at.get_or_insert_with(SystemTime::now);
+        self.attempts += 1;
+    }
+}
+
+/// The delivery mode of a notification accepted by APNs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// The notification was sent with an `apns_expiration` of 0. APNs tries
+    /// to deliver it once and discards it if the device is not reachable.
+    BestEffort,
+
+    /// APNs stores the notification and retries delivering it until it
+    /// expires.
+    Stored,
+}
+
+impl Delivery {
+    pub(crate) fn from_expiration(apns_expiration: Option<u64>) -> Self {
+        match apns_expiration {
+            Some(0) => Delivery::BestEffort,
+            _ => Delivery::Stored,
+        }
+    }
 }
 
 /// The response body from APNs. Only available for errors.
@@ -45,8 +176,40 @@ pub struct ErrorBody {
     pub timestamp: Option<u64>,
 }
 
-/// A description what went wrong with the push notification.
+/// The outcome of a single notification in a collection of responses, e.g.
+/// aggregated by a proxy sending to many device tokens.
+///
+/// ```rust
+/// # use apns_h2::{BatchResponseItem, ErrorReason};
+/// # fn main() {
+/// let items: Vec<BatchResponseItem> = serde_json::from_str(
+///     r#"[
+///         {"apns-id": "eabeae54-14a8-11e5-b60b-1697f925ec7b", "status": 200},
+///         {"apns-id": "e2b1d5a0-14a8-11e5-b60b-1697f925ec7b", "status": 410, "reason": "Unregistered"}
+///     ]"#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(None, items[0].reason);
+/// assert_eq!(Some(ErrorReason::Unregistered), items[1].reason);
+/// # }
+/// ```
 #[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct BatchResponseItem {
+    /// The `apns-id` of the notification.
+    #[serde(rename = "apns-id", alias = "apns_id")]
+    pub apns_id: Option<String>,
+
+    /// The HTTP status code APNs answered with.
+    pub status: u16,
+
+    /// The reason for the failure, if the notification was not accepted.
+    #[serde(default)]
+    pub reason: Option<ErrorReason>,
+}
+
+/// A description what went wrong with the push notification.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum ErrorReason {
     /// The collapse identifier exceeds the maximum allowed size.
     BadCollapseId,
@@ -205,6 +368,13 @@ mod tests {
     use super::*;
     use serde_json;
 
+    #[test]
+    fn test_delivery_from_expiration() {
+        assert_eq!(Delivery::Stored, Delivery::from_expiration(None));
+        assert_eq!(Delivery::BestEffort, Delivery::from_expiration(Some(0)));
+        assert_eq!(Delivery::Stored, Delivery::from_expiration(Some(1672531200)));
+    }
+
     #[test]
     fn test_error_response_parsing() {
         let errors = vec![
@@ -278,4 +448,65 @@ mod tests {
             assert_eq!(expected_body, response_body);
         }
     }
+
+    #[test]
+    fn test_batch_response_item_parsing() {
+        let items: Vec<BatchResponseItem> = serde_json::from_value(json!([
+            {"apns_id": "an_id", "status": 200},
+            {"apns-id": "another_id", "status": 400, "reason": "BadDeviceToken"},
+            {"status": 503, "reason": "ServiceUnavailable"},
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                BatchResponseItem {
+                    apns_id: Some(String::from("an_id")),
+                    status: 200,
+                    reason: None,
+                },
+                BatchResponseItem {
+                    apns_id: Some(String::from("another_id")),
+                    status: 400,
+                    reason: Some(ErrorReason::BadDeviceToken),
+                },
+                BatchResponseItem {
+                    apns_id: None,
+                    status: 503,
+                    reason: Some(ErrorReason::ServiceUnavailable),
+                },
+            ],
+            items
+        );
+    }
+
+    #[test]
+    fn test_audit_record() {
+        let request = http::Request::builder()
+            .header(http::header::AUTHORIZATION, "bearer secret")
+            .header(http::header::CONTENT_LENGTH, "42")
+            .header("apns-id", "00000000-0000-0000-0000-000000000001")
+            .body(())
+            .unwrap();
+
+        let mut audit = AuditRecord::new("abcdef0123456789");
+        audit.record_request(&request);
+        audit.record_request(&request);
+
+        assert_eq!("abcd********6789", audit.target);
+        assert_eq!(
+            vec![
+                (String::from("content-length"), String::from("42")),
+                (
+                    String::from("apns-id"),
+                    String::from("00000000-0000-0000-0000-000000000001")
+                ),
+            ],
+            audit.headers
+        );
+        assert_eq!(42, audit.body_bytes);
+        assert_eq!(Some("00000000-0000-0000-0000-000000000001"), audit.apns_id.as_deref());
+        assert!(audit.sent_at.is_some());
+        assert_eq!(2, audit.attempts);
+    }
 }
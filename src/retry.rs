@@ -0,0 +1,129 @@
+//! Retrying failed sends
+
+use crate::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+/// The future returned by [`RetryPolicy::next_delay`].
+pub type RetryDelay<'a> = Pin<Box<dyn Future<Output = Option<Duration>> + Send + 'a>>;
+
+/// Decides whether and when a failed send is retried.
+///
+/// Set with [`ClientConfig::retry_policy`](crate::ClientConfig::retry_policy).
+/// Without a policy, a failed send is not retried.
+///
+/// ```rust
+/// # use apns_h2::Error;
+/// # use apns_h2::retry::{RetryDelay, RetryPolicy};
+/// # use std::time::Duration;
+/// #[derive(Debug)]
+/// struct RetryTimeouts;
+///
+/// impl RetryPolicy for RetryTimeouts {
+///     fn next_delay<'a>(&'a self, attempt: u32, err: &'a Error) -> RetryDelay<'a> {
+///         Box::pin(async move {
+///             match err {
+///                 Error::RequestTimeout(_) if attempt <= 3 => Some(Duration::from_secs(1 << attempt)),
+///                 _ => None,
+///             }
+///         })
+///     }
+/// }
+/// ```
+pub trait RetryPolicy: fmt::Debug + Send + Sync {
+    /// The time to wait before sending again after send number `attempt`
+    /// failed with `err`, counting from 1. `None` stops retrying and returns
+    /// the error.
+    ///
+    /// The future may wait itself, e.g. for a global rate limiter.
+    fn next_delay<'a>(&'a self, attempt: u32, err: &'a Error) -> RetryDelay<'a>;
+}
+
+/// Retries errors that may pass, like timeouts, connection errors,
+/// [`Error::TooManyRequests`] and APNs being unavailable, doubling the delay
+/// after every attempt.
+///
+/// ```rust
+/// # use apns_h2::ClientConfig;
+/// # use apns_h2::retry::ExponentialBackoff;
+/// # use std::sync::Arc;
+/// # use std::time::Duration;
+/// let config = ClientConfig {
+///     retry_policy: Some(Arc::new(ExponentialBackoff {
+///         max_retries: 5,
+///         base_delay: Duration::from_millis(500),
+///     })),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoff {
+    /// The number of retries after the first send
+    pub max_retries: u32,
+    /// The delay before the first retry
+    pub base_delay: Duration,
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl ExponentialBackoff {
+    /// Whether sending again may succeed after `err`.
+    pub fn is_transient(err: &Error) -> bool {
+        match err {
+            Error::RequestTimeout(_) | Error::ConnectionError(_) | Error::ClientError(_) => true,
+            Error::TooManyRequests(_) => true,
+            Error::ResponseError(response) => matches!(response.code, 500 | 503),
+            _ => false,
+        }
+    }
+
+    /// The delay after send number `attempt` failed with a transient error.
+    fn delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt > self.max_retries {
+            return None;
+        }
+
+        Some(self.base_delay.saturating_mul(2u32.saturating_pow(attempt - 1)))
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn next_delay<'a>(&'a self, attempt: u32, err: &'a Error) -> RetryDelay<'a> {
+        let delay = if Self::is_transient(err) {
+            self.delay(attempt)
+        } else {
+            None
+        };
+
+        Box::pin(async move { delay })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_exponential_backoff() {
+        let policy = ExponentialBackoff::default();
+        let timeout = Error::RequestTimeout(20);
+
+        assert_eq!(Some(Duration::from_secs(1)), policy.next_delay(1, &timeout).await);
+        assert_eq!(Some(Duration::from_secs(2)), policy.next_delay(2, &timeout).await);
+        assert_eq!(Some(Duration::from_secs(4)), policy.next_delay(3, &timeout).await);
+        assert_eq!(None, policy.next_delay(4, &timeout).await);
+
+        let invalid = Error::InvalidTopic(String::from("example"));
+
+        assert_eq!(None, policy.next_delay(1, &invalid).await);
+    }
+}